@@ -0,0 +1,336 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use polars::prelude::LazyFrame;
+use tauri_test_lib::{
+    import_condition_data, load_condition_data, ConditionError, ConditionStore, ConditionWorkbook,
+    CsvImportOptions, ErrorKind, HolidayCalendar, ImportKind, LoadOptions, Locale, MergeStrategy,
+    ReportConfig,
+};
+
+/// Rhythm CareのCSVと前回のエクセルファイルから体調レポートを作成する
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// CSVとエクセルのデータを結合し、dataシートのみを保存する
+    Merge(PipelineArgs),
+    /// 結合したデータから年毎の集計シートとグラフを含むレポートを保存する
+    Report(ReportArgs),
+    /// 保存先への取り込みの履歴を新しい順に表示する
+    Imports(ImportsArgs),
+    /// 保存先への取り込みを取り消し、取り込む前の値に戻す
+    Rollback(RollbackArgs),
+}
+
+#[derive(Args)]
+struct PipelineArgs {
+    /// Rhythm Careからエクスポートしたcsvファイル (複数指定可、後に指定したものを優先)
+    #[arg(long)]
+    csv: Vec<PathBuf>,
+    /// 前回のエクセルファイル (無ければCSVのみからレポートを作成する)
+    #[arg(long)]
+    excel: Option<PathBuf>,
+    /// 保存先のエクセルファイル
+    #[arg(short, long)]
+    output: PathBuf,
+    /// csvのデータの前にある見出しの行数 (未指定なら自動判定)
+    #[arg(long)]
+    header_rows: Option<usize>,
+    /// csvの日付の書式 (例: %Y/%m/%d、未指定なら自動判定)
+    #[arg(long)]
+    date_format: Option<String>,
+    /// csvに複数人の列がある場合に読み込む人
+    #[arg(long)]
+    person: Option<String>,
+    /// csvにある全員の列を読み込み、人毎のシートと人別比較シートを作成する
+    #[arg(long)]
+    all_persons: bool,
+    /// 同じ日付のデータがCSVとエクセルの両方にある場合の扱い
+    #[arg(long, value_enum, default_value_t = StrategyArg::PreferCsv)]
+    merge_strategy: StrategyArg,
+    /// 値が異なっていた項目をconflictsシートに書き出す
+    #[arg(long)]
+    conflict_sheet: bool,
+    /// 読み込み時の検証結果を検証結果シートに書き出す
+    #[arg(long)]
+    diagnostics_sheet: bool,
+    /// シート名・グラフの配置・色・出力するシートを指定するレポート設定(JSON)
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// シート名・見出し・曜日・グラフのタイトルの言語 (未指定ならレポート設定の言語)
+    #[arg(long, value_enum)]
+    locale: Option<LocaleArg>,
+    /// 取り込んだデータを保存するSQLiteのファイル (指定するとCSVとエクセルをここへ結合し、
+    /// 保存されている全てのデータを書き出す。CSVもエクセルも無ければ保存されているデータのみ)
+    #[arg(long)]
+    store: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyArg {
+    /// エクセルの値を優先する
+    PreferExcel,
+    /// CSVの値を優先する
+    PreferCsv,
+    /// CSVを優先するが、CSVが空欄ならエクセルの値を残す
+    PreferNonNull,
+    /// コメントは両方を連結する
+    ConcatComments,
+}
+
+impl From<StrategyArg> for MergeStrategy {
+    fn from(arg: StrategyArg) -> Self {
+        match arg {
+            StrategyArg::PreferExcel => Self::PreferExcel,
+            StrategyArg::PreferCsv => Self::PreferCsv,
+            StrategyArg::PreferNonNull => Self::PreferNonNull,
+            StrategyArg::ConcatComments => Self::ConcatComments,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LocaleArg {
+    /// 日本語
+    Ja,
+    /// 英語
+    En,
+}
+
+impl From<LocaleArg> for Locale {
+    fn from(arg: LocaleArg) -> Self {
+        match arg {
+            LocaleArg::Ja => Self::Ja,
+            LocaleArg::En => Self::En,
+        }
+    }
+}
+
+impl PipelineArgs {
+    fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            csv: CsvImportOptions {
+                header_rows: self.header_rows,
+                date_format: self.date_format.clone(),
+                person: self.person.clone(),
+                all_persons: self.all_persons,
+            },
+            merge_strategy: self.merge_strategy.into(),
+            data_sheet: None,
+            scale: None,
+        }
+    }
+
+    fn report_config(&self) -> Result<ReportConfig, ConditionError> {
+        match &self.config {
+            Some(path) if !path.exists() => {
+                Err(ConditionError::FileNotFound { path: path.clone() })
+            }
+            Some(path) => ReportConfig::load(path),
+            None => Ok(ReportConfig::default()),
+        }
+    }
+
+    /// CSVとエクセルを読み込んで結合し、検証結果と値が異なっていた項目を表示する
+    fn load(&self, workbook: &mut ConditionWorkbook) -> Result<LazyFrame, ConditionError> {
+        let mut config = self.report_config()?;
+        if let Some(locale) = self.locale {
+            config.locale = locale.into();
+        }
+        let mut options = self.load_options();
+        options.data_sheet = Some(config.sheet_names.data.clone());
+        options.scale = Some(config.scale.clone());
+        workbook.set_config(config);
+        let merged = match &self.store {
+            Some(path) => {
+                let mut store = ConditionStore::open(path)?;
+                import_condition_data(&mut store, &self.csv, self.excel.as_ref(), &options)?
+            }
+            None => load_condition_data(&self.csv, self.excel.as_ref(), &options)?,
+        };
+        if let Some(batch) = &merged.import {
+            println!(
+                "import #{} saved to the store: {} added, {} changed",
+                batch.id, batch.added, batch.changed
+            );
+        }
+        for diagnostic in &merged.diagnostics {
+            eprintln!(
+                "warning: {}:{}: {} `{}` in {}",
+                diagnostic.path.display(),
+                diagnostic.row.map_or("-".into(), |row| row.to_string()),
+                diagnostic.kind.label(),
+                diagnostic.value.as_deref().unwrap_or_default(),
+                diagnostic.column,
+            );
+        }
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(empty)".into());
+        for conflict in &merged.conflicts {
+            eprintln!(
+                "conflict: {} {}: {} -> {} (kept {})",
+                conflict.date,
+                conflict.column,
+                show(&conflict.excel),
+                show(&conflict.csv),
+                show(&conflict.resolved),
+            );
+        }
+        if self.conflict_sheet {
+            workbook.set_conflicts(merged.conflicts);
+        }
+        if self.diagnostics_sheet {
+            workbook.set_diagnostics(merged.diagnostics);
+        }
+        Ok(merged.ldf)
+    }
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    /// 祝日に加えて休日として扱う日付 (YYYY-MM-DD、複数指定可)
+    #[arg(long = "holiday")]
+    holidays: Vec<NaiveDate>,
+    /// 前回のエクセルファイルのうち、データが変わった年のシートだけを作り直す
+    /// (他のシートや追加したシートはそのまま残し、既存の日付はエクセルの値を優先する)
+    #[arg(long, requires = "excel")]
+    update: bool,
+}
+
+#[derive(Args)]
+struct ImportsArgs {
+    /// 取り込んだデータを保存しているSQLiteのファイル
+    #[arg(long)]
+    store: PathBuf,
+}
+
+#[derive(Args)]
+struct RollbackArgs {
+    /// 取り込んだデータを保存しているSQLiteのファイル
+    #[arg(long)]
+    store: PathBuf,
+    /// 取り消す取り込みの番号 (importsで表示される番号)
+    id: i64,
+}
+
+/// エラー種別毎の終了コード (2はclapの引数エラーと同じ)
+fn exit_code(err: &ConditionError) -> u8 {
+    match err.kind() {
+        ErrorKind::NoInput => 2,
+        ErrorKind::FileNotFound => 3,
+        ErrorKind::CsvParse
+        | ErrorKind::CsvFormat
+        | ErrorKind::ExcelOpen
+        | ErrorKind::SheetNotFound
+        | ErrorKind::ColumnNotFound
+        | ErrorKind::Config
+        | ErrorKind::InvalidEntry
+        | ErrorKind::EntryExists
+        | ErrorKind::EntryNotFound
+        | ErrorKind::PersonRequired => 4,
+        ErrorKind::Data | ErrorKind::Report | ErrorKind::Internal => 5,
+        ErrorKind::Save => 6,
+        ErrorKind::Store => 7,
+        ErrorKind::Cancelled => 130,
+    }
+}
+
+fn run(command: Command) -> Result<(), ConditionError> {
+    let mut workbook = ConditionWorkbook::new();
+    match command {
+        Command::Merge(args) => {
+            let merged_ldf = args.load(&mut workbook)?;
+            workbook.write_data(&merged_ldf, &args.output)?;
+            println!("merged data saved to {}", args.output.display());
+        }
+        Command::Report(ReportArgs {
+            mut pipeline,
+            holidays,
+            update,
+        }) => {
+            if update {
+                pipeline.merge_strategy = StrategyArg::PreferExcel;
+            }
+            let merged_ldf = pipeline.load(&mut workbook)?;
+            workbook.set_holidays(HolidayCalendar::new().with_custom_holidays(holidays));
+            match &pipeline.excel {
+                Some(excel) if update => {
+                    let summary = workbook.update(&merged_ldf, excel, &pipeline.output)?;
+                    let years: Vec<String> =
+                        summary.years.iter().map(|year| year.to_string()).collect();
+                    println!(
+                        "report updated in {} (rewritten years: {}; kept sheets: {})",
+                        pipeline.output.display(),
+                        years.join(", "),
+                        summary.carried_sheets.join(", "),
+                    );
+                }
+                _ => {
+                    workbook.write(&merged_ldf, &pipeline.output)?;
+                    println!("report saved to {}", pipeline.output.display());
+                }
+            }
+        }
+        Command::Imports(ImportsArgs { store }) => {
+            for batch in ConditionStore::open(store)?.imports()? {
+                let sources: Vec<String> = batch
+                    .sources
+                    .iter()
+                    .map(|source| format!("{} ({})", source.path.display(), &source.sha256[..12]))
+                    .collect();
+                let kind = match batch.kind {
+                    ImportKind::Files => "",
+                    ImportKind::Manual => " (manual edit)",
+                };
+                let rolled_back = batch
+                    .rolled_back_at
+                    .map(|at| format!(" [rolled back {}]", at.format("%Y-%m-%d %H:%M")))
+                    .unwrap_or_default();
+                println!(
+                    "#{} {}{kind}: {} added, {} changed{rolled_back}\n    {}",
+                    batch.id,
+                    batch.imported_at.format("%Y-%m-%d %H:%M"),
+                    batch.added,
+                    batch.changed,
+                    sources.join("\n    "),
+                );
+            }
+        }
+        Command::Rollback(RollbackArgs { store, id }) => {
+            let summary = ConditionStore::open(store)?.rollback(id)?;
+            println!("import #{id} rolled back: {} restored", summary.restored);
+            for entry in &summary.skipped {
+                eprintln!(
+                    "skipped: {}{} was changed by a later import",
+                    entry.date,
+                    entry
+                        .person
+                        .as_ref()
+                        .map(|person| format!(" {person}"))
+                        .unwrap_or_default(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::locale::Locale;
+use crate::scale::ScoreScale;
+
+/// アプリの設定ディレクトリに保存するレポート設定のファイル名
+pub const REPORT_CONFIG_FILE: &str = "report_config.json";
+
+/// レポートのシート名・グラフの配置・色・出力するシートの設定
+///
+/// 設定ファイルに無い項目は既定値を使うため、変更したい項目だけを書けばよい。
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReportConfig {
+    pub sheet_names: SheetNames,
+    pub chart_layout: ChartLayoutConfig,
+    pub colors: ReportColors,
+    pub sections: ReportSections,
+    /// 体調の値の範囲と値毎の表示 (検証・集計表・グラフの軸に使う)
+    pub scale: ScoreScale,
+    /// シート名・見出し・曜日・グラフのタイトルの言語 (既定は日本語)
+    pub locale: Locale,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SheetNames {
+    pub data: String,
+    /// 年毎の集計表を並べるシート (複数人のデータでは後ろに人の名前が付く)
+    pub comparison: String,
+    pub weekday_analysis: String,
+    /// 未記録の期間と月毎の記録率を並べるシート
+    pub missing_days: String,
+    pub person_comparison: String,
+    pub conflicts: String,
+    pub diagnostics: String,
+}
+
+impl Default for SheetNames {
+    fn default() -> Self {
+        Self {
+            data: "data".into(),
+            comparison: "年間体調比較".into(),
+            weekday_analysis: "曜日別分析".into(),
+            missing_days: "未記録日".into(),
+            person_comparison: "人別比較".into(),
+            conflicts: "conflicts".into(),
+            diagnostics: "検証結果".into(),
+        }
+    }
+}
+
+impl SheetNames {
+    /// 全てのシート名 (設定の確認や更新時に前回のレポートのシートを判別するため)
+    pub fn all(&self) -> [&str; 7] {
+        [
+            &self.data,
+            &self.comparison,
+            &self.weekday_analysis,
+            &self.missing_days,
+            &self.person_comparison,
+            &self.conflicts,
+            &self.diagnostics,
+        ]
+    }
+
+    /// 既定のままのシート名をレポートの言語にする (変更したシート名はそのまま)
+    pub fn localized(&self, locale: Locale) -> Self {
+        let localize = |name: &String, default: &'static str| {
+            if *name == default {
+                locale.text(default).to_string()
+            } else {
+                name.clone()
+            }
+        };
+        Self {
+            data: self.data.clone(),
+            comparison: localize(&self.comparison, "年間体調比較"),
+            weekday_analysis: localize(&self.weekday_analysis, "曜日別分析"),
+            missing_days: localize(&self.missing_days, "未記録日"),
+            person_comparison: localize(&self.person_comparison, "人別比較"),
+            conflicts: self.conflicts.clone(),
+            diagnostics: localize(&self.diagnostics, "検証結果"),
+        }
+    }
+}
+
+/// 年毎のシートに並べる月毎のグラフの配置
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChartLayoutConfig {
+    /// 横に並べるグラフの数 (12か月分を折り返して並べる)
+    pub columns: usize,
+    /// 最初のグラフを挿入する行
+    pub start_row: usize,
+    /// グラフの配置間隔がセルで何個分か
+    pub row_offset: usize,
+    pub column_offset: usize,
+    /// グラフの大きさ (ピクセル)
+    pub width: u32,
+    pub height: u32,
+    /// 年毎の集計表と人毎の比較のシートに挿入するグラフの大きさ (ピクセル)
+    pub summary_width: u32,
+    pub summary_height: u32,
+}
+
+impl Default for ChartLayoutConfig {
+    fn default() -> Self {
+        Self {
+            columns: 2,
+            start_row: 13,
+            row_offset: 8,
+            column_offset: 11,
+            width: 620,
+            height: 155,
+            summary_width: 620,
+            summary_height: 240,
+        }
+    }
+}
+
+/// レポートで使う色 (`#RRGGBB`形式)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReportColors {
+    /// 月毎のグラフで土日祝の背景に使う色
+    pub holiday_fill: String,
+    pub trend_line: String,
+    pub rolling_mean_line: String,
+    /// 集計表の年間の日数のデータバー
+    pub annual_bar: String,
+    /// 集計表の月毎の日数のデータバー
+    pub monthly_bar: String,
+    /// 年毎のシートで最後の記録までの未記録の日の行に付ける色
+    pub missing_fill: String,
+}
+
+impl Default for ReportColors {
+    fn default() -> Self {
+        Self {
+            holiday_fill: "#FBE5D6".into(),
+            trend_line: "#0000FF".into(),
+            rolling_mean_line: "#FF6600".into(),
+            annual_bar: "#FF6600".into(),
+            monthly_bar: "#008000".into(),
+            missing_fill: "#FFEB9C".into(),
+        }
+    }
+}
+
+/// 出力するシートとグラフ
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReportSections {
+    pub monthly_charts: bool,
+    pub year_comparison_charts: bool,
+    pub weekday_analysis: bool,
+    pub missing_days: bool,
+    pub person_comparison: bool,
+    /// 年毎のシートで未記録の日の行に色を付ける (後から記録を補う日を探すため)
+    pub highlight_missing_days: bool,
+}
+
+impl Default for ReportSections {
+    fn default() -> Self {
+        Self {
+            monthly_charts: true,
+            year_comparison_charts: true,
+            weekday_analysis: true,
+            missing_days: true,
+            person_comparison: true,
+            highlight_missing_days: false,
+        }
+    }
+}
+
+impl ReportConfig {
+    /// 設定ファイルを読み込む (ファイルが無ければ既定の設定を返す)
+    pub fn load(path: &Path) -> ConditionResult<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ConditionError::config(path, e)),
+        };
+        let config: Self =
+            serde_json::from_str(&text).map_err(|e| ConditionError::config(path, e))?;
+        config
+            .validate()
+            .map_err(|message| ConditionError::config(path, message))?;
+        Ok(config)
+    }
+
+    /// 検証してから設定ファイルに保存する
+    pub fn save(&self, path: &Path) -> ConditionResult<()> {
+        self.validate()
+            .map_err(|message| ConditionError::config(path, message))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ConditionError::config(path, e))?;
+        }
+        let text =
+            serde_json::to_string_pretty(self).map_err(|e| ConditionError::config(path, e))?;
+        fs::write(path, text).map_err(|e| ConditionError::config(path, e))
+    }
+
+    /// エクセルで使えないシート名や色、グラフが並ばない配置を確認する
+    pub fn validate(&self) -> Result<(), String> {
+        let names = &self.sheet_names;
+        let mut seen = HashSet::new();
+        for name in names.all() {
+            if name.is_empty() || name.chars().count() > 31 {
+                return Err(format!("sheet name `{name}` must be 1 to 31 characters"));
+            }
+            if name.contains(['[', ']', ':', '*', '?', '/', '\\']) {
+                return Err(format!(
+                    "sheet name `{name}` contains a character Excel does not allow"
+                ));
+            }
+            if !seen.insert(name) {
+                return Err(format!("sheet name `{name}` is used more than once"));
+            }
+        }
+
+        let colors = &self.colors;
+        let level_colors = self
+            .scale
+            .levels
+            .iter()
+            .filter_map(|level| level.color.as_ref());
+        for color in [
+            &colors.holiday_fill,
+            &colors.trend_line,
+            &colors.rolling_mean_line,
+            &colors.annual_bar,
+            &colors.monthly_bar,
+            &colors.missing_fill,
+        ]
+        .into_iter()
+        .chain(level_colors)
+        {
+            let valid = color
+                .strip_prefix('#')
+                .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+            if !valid {
+                return Err(format!("color `{color}` must be in #RRGGBB format"));
+            }
+        }
+        self.scale.validate()?;
+
+        let layout = &self.chart_layout;
+        let sizes = [
+            layout.width,
+            layout.height,
+            layout.summary_width,
+            layout.summary_height,
+        ];
+        if layout.columns == 0 || sizes.contains(&0) {
+            return Err("chart columns, width and height must be greater than 0".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_load_partial_config() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(REPORT_CONFIG_FILE);
+        assert_eq!(ReportConfig::load(&path).unwrap(), ReportConfig::default());
+
+        fs::write(
+            &path,
+            r##"{"sheetNames": {"comparison": "比較"}, "colors": {"holidayFill": "#DDEBF7"}}"##,
+        )
+        .unwrap();
+        let config = ReportConfig::load(&path).unwrap();
+        assert_eq!(config.sheet_names.comparison, "比較");
+        assert_eq!(config.sheet_names.data, "data");
+        assert_eq!(config.colors.holiday_fill, "#DDEBF7");
+        assert_eq!(config.chart_layout, ChartLayoutConfig::default());
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_config() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config").join(REPORT_CONFIG_FILE);
+        let mut config = ReportConfig::default();
+        config.sheet_names.weekday_analysis = "data".into();
+        let err = config.save(&path).unwrap_err();
+        assert!(matches!(err, ConditionError::Config { .. }));
+        assert!(!path.exists());
+
+        config.sheet_names.weekday_analysis = "曜日".into();
+        config.colors.monthly_bar = "green".into();
+        assert!(config.save(&path).is_err());
+
+        config.colors.monthly_bar = "#00B050".into();
+        config.scale.levels[0].color = Some("#00B05".into());
+        assert!(config.save(&path).is_err());
+
+        config.scale.levels[0].color = None;
+        config.scale.max = 6;
+        assert!(config.save(&path).is_err());
+
+        config.scale.max = 5;
+        config.save(&path).unwrap();
+        assert_eq!(ReportConfig::load(&path).unwrap(), config);
+        temp_dir.close().unwrap();
+    }
+}
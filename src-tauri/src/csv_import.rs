@@ -0,0 +1,468 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use encoding_rs::{SHIFT_JIS, UTF_16BE, UTF_16LE};
+use polars::prelude::*;
+use serde::Deserialize;
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::schema::{ConditionRecord, DATE_COLUMN};
+use crate::validate::{Diagnostic, DiagnosticKind, ROW_COLUMN};
+
+/// 自動判定で試す日付の書式 (曖昧な場合は先にあるものを優先する)
+const DATE_FORMATS: [&str; 7] = [
+    "%Y/%m/%d",
+    "%Y-%m-%d",
+    "%Y.%m.%d",
+    "%Y年%m月%d日",
+    "%Y%m%d",
+    "%m/%d/%Y",
+    "%d/%m/%Y",
+];
+
+/// Rhythm CareのCSVの読み込み設定
+///
+/// 未指定の項目はファイルの内容から自動で判定する。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvImportOptions {
+    /// データの前にある見出しの行数
+    pub header_rows: Option<usize>,
+    /// 日付の書式 (chronoの書式指定子)
+    pub date_format: Option<String>,
+    /// 複数人の列がある場合に読み込む人 (見出しの部分一致、未指定なら最初の人)
+    pub person: Option<String>,
+    /// 全員の列を読み込み、誰のデータかを「人」の列に残す
+    ///
+    /// `person`も指定した場合は一致する人のみを読み込む。
+    pub all_persons: bool,
+}
+
+/// 見出しから判定した1人分の列
+#[derive(Debug)]
+struct PersonColumns {
+    label: String,
+    condition: usize,
+    comment: Option<usize>,
+}
+
+/// Rhythm CareのCSVを読み込み、日付・体調・コメントの列に変換する
+pub fn read_rhythm_care_csv(path: &Path, options: &CsvImportOptions) -> ConditionResult<DataFrame> {
+    Ok(read_rhythm_care_csv_with_rows(path, options, &mut vec![])?.drop(ROW_COLUMN)?)
+}
+
+/// 検証結果で元の行を示せるよう、CSVでの行番号の列を残して読み込む
+///
+/// 読み取れない日付や体調は空欄として読み込み、`diagnostics`に追加する。
+pub(crate) fn read_rhythm_care_csv_with_rows(
+    path: &Path,
+    options: &CsvImportOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> ConditionResult<DataFrame> {
+    let bytes = fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => ConditionError::FileNotFound { path: path.into() },
+        _ => ConditionError::CsvParse {
+            path: path.into(),
+            message: e.to_string(),
+        },
+    })?;
+    let text = decode(path, &bytes)?;
+
+    // 型を推定せず全ての列を文字列として読み込む
+    let raw_df = CsvReadOptions::default()
+        .with_has_header(false)
+        .with_infer_schema_length(Some(0))
+        .into_reader_with_file_handle(Cursor::new(text.into_bytes()))
+        .finish()
+        .map_err(|e| ConditionError::csv(path, e))?;
+    let rows = raw_rows(&raw_df)?;
+
+    let (header_rows, date_col) = match options.header_rows {
+        Some(header_rows) => (header_rows, 0),
+        None => detect_data_start(&rows).ok_or_else(|| ConditionError::CsvFormat {
+            path: path.into(),
+            message: "no row starting with a date was found".into(),
+            found: rows.iter().take(3).map(|row| join_row(row)).collect(),
+        })?,
+    };
+    let (headers, data) = rows.split_at(header_rows.min(rows.len()));
+
+    let date_format = match &options.date_format {
+        Some(date_format) => date_format.clone(),
+        None => detect_date_format(data, date_col).ok_or_else(|| ConditionError::CsvFormat {
+            path: path.into(),
+            message: "the date format could not be detected".into(),
+            found: data
+                .iter()
+                .filter_map(|row| row[date_col].clone())
+                .take(5)
+                .collect(),
+        })?,
+    };
+
+    let persons = detect_person_columns(headers, date_col, raw_df.width());
+    let matches = |p: &&PersonColumns| match &options.person {
+        Some(name) => p.label.contains(name.as_str()),
+        None => true,
+    };
+    let selected: Vec<&PersonColumns> = if options.all_persons {
+        persons.iter().filter(matches).collect()
+    } else {
+        persons.iter().find(matches).into_iter().collect()
+    };
+    if selected.is_empty() {
+        return Err(ConditionError::CsvFormat {
+            path: path.into(),
+            message: match &options.person {
+                Some(name) => format!("no condition column for `{name}` was found"),
+                None => "no condition column was found".into(),
+            },
+            found: persons.iter().map(|p| p.label.clone()).collect(),
+        });
+    }
+
+    let mut row_numbers: Vec<u32> = vec![];
+    let mut records: Vec<ConditionRecord> = vec![];
+    for (i, row) in data.iter().enumerate() {
+        let row_number = header_rows + i + 1;
+        // 日付の無い行は読み飛ばす
+        let Some(date_cell) = &row[date_col] else {
+            continue;
+        };
+        // 読み取れない日付の行は検証結果に残して読み飛ばす
+        let Ok(date) = NaiveDate::parse_from_str(date_cell, &date_format) else {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Unparseable,
+                path: path.into(),
+                row: Some(row_number),
+                date: None,
+                column: DATE_COLUMN.into(),
+                value: Some(date_cell.clone()),
+            });
+            continue;
+        };
+        for person in &selected {
+            let condition = match &row[person.condition] {
+                Some(cell) => {
+                    let condition = parse_condition(cell);
+                    if condition.is_none() {
+                        diagnostics.push(Diagnostic {
+                            kind: DiagnosticKind::Unparseable,
+                            path: path.into(),
+                            row: Some(row_number),
+                            date: Some(date),
+                            column: person.label.clone(),
+                            value: Some(cell.clone()),
+                        });
+                    }
+                    condition
+                }
+                None => None,
+            };
+            let comment = person.comment.and_then(|idx| row[idx].clone());
+
+            row_numbers.push(row_number as u32);
+            records.push(ConditionRecord {
+                date: Some(date),
+                person: Some(person_name(&person.label)),
+                condition,
+                comment,
+            });
+        }
+    }
+
+    let mut df = ConditionRecord::to_frame(&records, options.all_persons)?;
+    df.with_column(Series::new(ROW_COLUMN.into(), row_numbers))?;
+    let df = df.sort(
+        [DATE_COLUMN],
+        SortMultipleOptions::new()
+            .with_order_descending(false)
+            .with_nulls_last(true)
+            .with_maintain_order(true),
+    )?;
+    Ok(df)
+}
+
+/// 見出しから人の名前を取り出す (「愛さん体調」→「愛さん」)
+fn person_name(label: &str) -> String {
+    match label.strip_suffix("体調").map(str::trim) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => label.to_string(),
+    }
+}
+
+/// 文字コードを判定して文字列に変換する
+///
+/// BOM付きのUTF-8/UTF-16、BOM無しのUTF-8、Shift_JIS(CP932の拡張文字を含む)に対応する。
+fn decode(path: &Path, bytes: &[u8]) -> ConditionResult<String> {
+    if let Some(bytes) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return decode_utf8(path, bytes);
+    }
+    for (bom, encoding) in [(b"\xFF\xFE", UTF_16LE), (b"\xFE\xFF", UTF_16BE)] {
+        if let Some(bytes) = bytes.strip_prefix(bom) {
+            return encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(String::from)
+                .ok_or_else(|| ConditionError::CsvFormat {
+                    path: path.into(),
+                    message: format!("the file is not valid {}", encoding.name()),
+                    found: vec![],
+                });
+        }
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(text.into());
+    }
+    // Windowsで保存されたCSVはShift_JIS(CP932)であることが多い
+    match SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(text) => Ok(text.into()),
+        None => decode_utf8(path, bytes),
+    }
+}
+
+fn decode_utf8(path: &Path, bytes: &[u8]) -> ConditionResult<String> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| ConditionError::CsvFormat {
+        path: path.into(),
+        message: "the file is neither UTF-8, UTF-16 nor Shift_JIS encoded".into(),
+        found: vec![format!(
+            "invalid byte at offset {}",
+            e.utf8_error().valid_up_to()
+        )],
+    })
+}
+
+/// 全ての列を文字列の行に変換する (空欄はNone)
+fn raw_rows(raw_df: &DataFrame) -> PolarsResult<Vec<Vec<Option<String>>>> {
+    let columns = raw_df
+        .get_columns()
+        .iter()
+        .map(|c| c.str().cloned())
+        .collect::<PolarsResult<Vec<_>>>()?;
+    Ok((0..raw_df.height())
+        .map(|i| {
+            columns
+                .iter()
+                .map(|c| {
+                    c.get(i)
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                        .map(String::from)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn join_row(row: &[Option<String>]) -> String {
+    row.iter()
+        .map(|cell| cell.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 最初に日付で始まる行を探し、見出しの行数と日付の列を返す
+fn detect_data_start(rows: &[Vec<Option<String>>]) -> Option<(usize, usize)> {
+    rows.iter().enumerate().find_map(|(i, row)| {
+        row.iter()
+            .position(|cell| cell.as_deref().is_some_and(|v| parse_any_date(v).is_some()))
+            .map(|col| (i, col))
+    })
+}
+
+fn parse_any_date(value: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// 最も多くの日付を解釈できる書式を探す (解釈できない日付は検証結果に残す)
+fn detect_date_format(data: &[Vec<Option<String>>], date_col: usize) -> Option<String> {
+    let values: Vec<&str> = data
+        .iter()
+        .filter_map(|row| row[date_col].as_deref())
+        .collect();
+    let mut best: Option<(&str, usize)> = None;
+    for format in DATE_FORMATS {
+        let parsed = values
+            .iter()
+            .filter(|v| NaiveDate::parse_from_str(v, format).is_ok())
+            .count();
+        if parsed > best.map_or(0, |(_, count)| count) {
+            best = Some((format, parsed));
+        }
+    }
+    best.map(|(format, _)| format.to_string())
+}
+
+/// 見出しから人毎の体調とコメントの列を判定する
+///
+/// Rhythm Careは1人につき体調とコメントの2列を出力し、コメントの列は見出しに「コメント」を含む。
+/// 見出しが無い場合は日付・体調・コメントの順に並んでいるものとする。
+fn detect_person_columns(
+    headers: &[Vec<Option<String>>],
+    date_col: usize,
+    width: usize,
+) -> Vec<PersonColumns> {
+    let mut columns = (0..width).filter(|&c| c != date_col);
+    if headers.is_empty() {
+        return match columns.next() {
+            Some(condition) => vec![PersonColumns {
+                label: "体調".into(),
+                condition,
+                comment: columns.next(),
+            }],
+            None => vec![],
+        };
+    }
+
+    let mut persons: Vec<PersonColumns> = vec![];
+    for col in columns {
+        let labels: Vec<&str> = headers.iter().filter_map(|r| r[col].as_deref()).collect();
+        let is_comment = labels
+            .iter()
+            .any(|l| l.contains("コメント") || l.to_lowercase().contains("comment"));
+        if !is_comment {
+            persons.push(PersonColumns {
+                label: labels.first().map_or("体調", |l| l).to_string(),
+                condition: col,
+                comment: None,
+            });
+        } else if let Some(person) = persons.last_mut().filter(|p| p.comment.is_none()) {
+            person.comment = Some(col);
+        }
+    }
+    persons
+}
+
+fn parse_condition(value: &str) -> Option<i32> {
+    value.parse::<i32>().ok().or_else(|| {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.fract() == 0.0)
+            .map(|v| v as i32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn read(content: &str, options: &CsvImportOptions) -> ConditionResult<DataFrame> {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.csv");
+        fs::write(&file_path, content).unwrap();
+        read_rhythm_care_csv(&file_path, options)
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_single_header_row_with_iso_dates() {
+        let df = read(
+            "日付,体調,コメント\n2025-01-27,3,\n2025-01-28,4,good\n",
+            &CsvImportOptions::default(),
+        )
+        .unwrap();
+        let expected_df = df!(
+            "日付" => [date(2025, 1, 27), date(2025, 1, 28)],
+            "体調" => [3i32, 4],
+            "コメント" => [None, Some("good")]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
+    #[test]
+    fn test_multiple_persons() {
+        let content = "日付,\"愛さん体調\",\"愛さん体調\",\"太郎さん体調\",\"太郎さん体調\"\n\
+                       ,\"\",コメント,\"\",コメント\n\
+                       2025/1/27,2,愛,5,太郎\n";
+        let df = read(content, &CsvImportOptions::default()).unwrap();
+        assert_eq!(df.column("体調").unwrap().i32().unwrap().get(0), Some(2));
+
+        let options = CsvImportOptions {
+            person: Some("太郎".into()),
+            ..Default::default()
+        };
+        let df = read(content, &options).unwrap();
+        assert_eq!(df.column("体調").unwrap().i32().unwrap().get(0), Some(5));
+        assert_eq!(
+            df.column("コメント").unwrap().str().unwrap().get(0),
+            Some("太郎")
+        );
+    }
+
+    #[test]
+    fn test_all_persons() {
+        let content = "日付,\"愛さん体調\",\"愛さん体調\",\"太郎さん体調\",\"太郎さん体調\"\n\
+                       ,\"\",コメント,\"\",コメント\n\
+                       2025/1/27,2,愛,5,太郎\n\
+                       2025/1/28,3,,,\n";
+        let options = CsvImportOptions {
+            all_persons: true,
+            ..Default::default()
+        };
+        let df = read(content, &options).unwrap();
+        let expected_df = df!(
+            "日付" => [date(2025, 1, 27), date(2025, 1, 27), date(2025, 1, 28), date(2025, 1, 28)],
+            "人" => ["愛さん", "太郎さん", "愛さん", "太郎さん"],
+            "体調" => [Some(2i32), Some(5), Some(3), None],
+            "コメント" => [Some("愛"), Some("太郎"), None, None]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
+    #[test]
+    fn test_undetectable_format_lists_found_values() {
+        let err = read("名前,値\nfoo,1\n", &CsvImportOptions::default()).unwrap_err();
+        let ConditionError::CsvFormat { found, .. } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(found, &["名前,値", "foo,1"]);
+    }
+
+    #[test]
+    fn test_unparseable_cells_are_reported() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.csv");
+        fs::write(
+            &file_path,
+            "日付,体調,コメント\n2025/01/27,3,\n2025/01/28,good,\n2025/13/45,2,\n2025/02/30,4,\n",
+        )
+        .unwrap();
+        let mut diagnostics = vec![];
+        let df = read_rhythm_care_csv_with_rows(
+            &file_path,
+            &CsvImportOptions::default(),
+            &mut diagnostics,
+        )
+        .unwrap();
+        // 読み取れない日付の行は読み込まない
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.column("体調").unwrap().null_count(), 1);
+        assert_eq!(df.column("日付").unwrap().null_count(), 0);
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.row, d.date, d.column.as_str(), d.value.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some(3), Some(date(2025, 1, 28)), "体調", Some("good")),
+                (Some(4), None, "日付", Some("2025/13/45")),
+                (Some(5), None, "日付", Some("2025/02/30")),
+            ]
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::Unparseable));
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use polars::prelude::PolarsError;
+use serde::{Serialize, Serializer};
+
+/// フロントエンドでエラーメッセージを出し分けるためのエラー種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    NoInput,
+    FileNotFound,
+    CsvParse,
+    CsvFormat,
+    ExcelOpen,
+    SheetNotFound,
+    ColumnNotFound,
+    Data,
+    Report,
+    Save,
+    Cancelled,
+    Internal,
+    Config,
+    Store,
+    InvalidEntry,
+    EntryExists,
+    EntryNotFound,
+    PersonRequired,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConditionError {
+    #[error("no CSV or Excel file was given")]
+    NoInput,
+
+    #[error("file not found: {}", path.display())]
+    FileNotFound { path: PathBuf },
+
+    /// 読み取れない日付や体調はエラーにせず検証結果に残すため、行や列は持たない
+    #[error("failed to parse CSV {}: {message}", path.display())]
+    CsvParse { path: PathBuf, message: String },
+
+    #[error("unrecognized CSV format in {}: {message} (found: {})", path.display(), found.join(" | "))]
+    CsvFormat {
+        path: PathBuf,
+        message: String,
+        found: Vec<String>,
+    },
+
+    #[error("failed to open Excel file {}: {message}", path.display())]
+    ExcelOpen { path: PathBuf, message: String },
+
+    #[error("sheet `{sheet}` not found in {}", path.display())]
+    SheetNotFound { path: PathBuf, sheet: String },
+
+    #[error("column `{column}` not found in sheet `{sheet}` of {}", path.display())]
+    ColumnNotFound {
+        path: PathBuf,
+        sheet: String,
+        column: String,
+    },
+
+    #[error(transparent)]
+    Data(#[from] PolarsError),
+
+    #[error(transparent)]
+    Report(#[from] rust_xlsxwriter::XlsxError),
+
+    #[error("failed to save {}: {message}", path.display())]
+    Save { path: PathBuf, message: String },
+
+    #[error("report generation was cancelled")]
+    Cancelled,
+
+    #[error("report generation stopped unexpectedly: {0}")]
+    Internal(String),
+
+    #[error("invalid report config {}: {message}", path.display())]
+    Config { path: PathBuf, message: String },
+
+    #[error("failed to access data store {}: {message}", path.display())]
+    Store { path: PathBuf, message: String },
+
+    #[error("invalid entry for {date}: {message}")]
+    InvalidEntry { date: NaiveDate, message: String },
+
+    #[error("an entry for {date}{} already exists", person_suffix(person))]
+    EntryExists {
+        date: NaiveDate,
+        person: Option<String>,
+    },
+
+    #[error("no entry for {date}{}", person_suffix(person))]
+    EntryNotFound {
+        date: NaiveDate,
+        person: Option<String>,
+    },
+
+    /// 人の列の無いファイルを複数人のデータに結合する場合は、誰のデータかを選ぶ必要がある
+    #[error("the data has several persons ({}); choose which person the file without a person column belongs to", persons.join(", "))]
+    PersonRequired { persons: Vec<String> },
+}
+
+fn person_suffix(person: &Option<String>) -> String {
+    person
+        .as_ref()
+        .map(|person| format!(" ({person})"))
+        .unwrap_or_default()
+}
+
+pub type ConditionResult<T> = Result<T, ConditionError>;
+
+impl ConditionError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoInput => ErrorKind::NoInput,
+            Self::FileNotFound { .. } => ErrorKind::FileNotFound,
+            Self::CsvParse { .. } => ErrorKind::CsvParse,
+            Self::CsvFormat { .. } => ErrorKind::CsvFormat,
+            Self::ExcelOpen { .. } => ErrorKind::ExcelOpen,
+            Self::SheetNotFound { .. } => ErrorKind::SheetNotFound,
+            Self::ColumnNotFound { .. } => ErrorKind::ColumnNotFound,
+            Self::Data(_) => ErrorKind::Data,
+            Self::Report(_) => ErrorKind::Report,
+            Self::Save { .. } => ErrorKind::Save,
+            Self::Cancelled => ErrorKind::Cancelled,
+            Self::Internal(_) => ErrorKind::Internal,
+            Self::Config { .. } => ErrorKind::Config,
+            Self::Store { .. } => ErrorKind::Store,
+            Self::InvalidEntry { .. } => ErrorKind::InvalidEntry,
+            Self::EntryExists { .. } => ErrorKind::EntryExists,
+            Self::EntryNotFound { .. } => ErrorKind::EntryNotFound,
+            Self::PersonRequired { .. } => ErrorKind::PersonRequired,
+        }
+    }
+
+    /// エラーの原因となったファイル
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::FileNotFound { path }
+            | Self::CsvParse { path, .. }
+            | Self::CsvFormat { path, .. }
+            | Self::ExcelOpen { path, .. }
+            | Self::SheetNotFound { path, .. }
+            | Self::ColumnNotFound { path, .. }
+            | Self::Save { path, .. }
+            | Self::Config { path, .. }
+            | Self::Store { path, .. } => Some(path),
+            Self::NoInput
+            | Self::Data(_)
+            | Self::Report(_)
+            | Self::Cancelled
+            | Self::Internal(_)
+            | Self::InvalidEntry { .. }
+            | Self::EntryExists { .. }
+            | Self::EntryNotFound { .. }
+            | Self::PersonRequired { .. } => None,
+        }
+    }
+
+    /// CSVの読み込みエラーを変換する
+    pub(crate) fn csv(path: &Path, err: PolarsError) -> Self {
+        match err {
+            PolarsError::IO { error, .. } if error.kind() == io::ErrorKind::NotFound => {
+                Self::FileNotFound { path: path.into() }
+            }
+            err => Self::CsvParse {
+                path: path.into(),
+                message: err.to_string(),
+            },
+        }
+    }
+
+    /// エクセルファイルを開く際のエラーを変換する
+    pub(crate) fn excel_open(path: &Path, err: calamine::XlsxError) -> Self {
+        match err {
+            calamine::XlsxError::Io(e) if e.kind() == io::ErrorKind::NotFound => {
+                Self::FileNotFound { path: path.into() }
+            }
+            err => Self::ExcelOpen {
+                path: path.into(),
+                message: err.to_string(),
+            },
+        }
+    }
+
+    /// 設定ファイルの読み書きや検証のエラーを変換する
+    pub(crate) fn config(path: &Path, err: impl std::fmt::Display) -> Self {
+        Self::Config {
+            path: path.into(),
+            message: err.to_string(),
+        }
+    }
+
+    /// 前回のエクセルファイルの中身(xlsxのパッケージ)を読み取れなかったエラーを変換する
+    pub(crate) fn excel_package(path: &Path, err: impl std::fmt::Display) -> Self {
+        Self::ExcelOpen {
+            path: path.into(),
+            message: err.to_string(),
+        }
+    }
+
+    /// 保存先のデータベースの読み書きのエラーを変換する
+    pub(crate) fn store(path: &Path, err: impl std::fmt::Display) -> Self {
+        Self::Store {
+            path: path.into(),
+            message: err.to_string(),
+        }
+    }
+
+    /// ワークブックの保存エラーを変換する
+    pub(crate) fn save(path: &Path, err: impl std::fmt::Display) -> Self {
+        Self::Save {
+            path: path.into(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Tauriの境界を越えてフロントエンドに渡す形式
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedError<'a> {
+    kind: ErrorKind,
+    message: String,
+    path: Option<&'a Path>,
+    column: Option<&'a str>,
+    found: &'a [String],
+}
+
+impl Serialize for ConditionError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let column = match self {
+            Self::ColumnNotFound { column, .. } => Some(column.as_str()),
+            _ => None,
+        };
+        let found = match self {
+            Self::CsvFormat { found, .. } => found.as_slice(),
+            _ => &[],
+        };
+        SerializedError {
+            kind: self.kind(),
+            message: self.to_string(),
+            path: self.path(),
+            column,
+            found,
+        }
+        .serialize(serializer)
+    }
+}
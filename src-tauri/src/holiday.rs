@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+/// 利用者が独自に指定した休日の名称
+const CUSTOM_HOLIDAY_NAME: &str = "休日";
+
+/// 日本の祝日と利用者指定の休日をまとめたカレンダー
+///
+/// 祝日は2000年以降の「国民の祝日に関する法律」に基づいて年毎に計算する。
+#[derive(Debug, Clone, Default)]
+pub struct HolidayCalendar {
+    custom_holidays: Vec<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 祝日に加えて休日として扱う日付を追加する
+    pub fn with_custom_holidays(mut self, dates: impl IntoIterator<Item = NaiveDate>) -> Self {
+        self.custom_holidays.extend(dates);
+        self
+    }
+
+    /// 利用者が指定した休日
+    pub fn custom_holidays(&self) -> &[NaiveDate] {
+        &self.custom_holidays
+    }
+
+    /// 指定した年の休日を日付順に返す
+    pub fn holidays(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        let mut holidays: BTreeMap<NaiveDate, String> = national_holidays(year)
+            .into_iter()
+            .map(|(date, name)| (date, name.to_string()))
+            .collect();
+        for date in self.custom_holidays.iter().filter(|d| d.year() == year) {
+            holidays
+                .entry(*date)
+                .or_insert_with(|| CUSTOM_HOLIDAY_NAME.to_string());
+        }
+        holidays.into_iter().collect()
+    }
+}
+
+/// 振替休日と国民の休日を含む、指定した年の祝日
+fn national_holidays(year: i32) -> BTreeMap<NaiveDate, &'static str> {
+    let ymd = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let mut holidays = BTreeMap::new();
+
+    holidays.insert(ymd(1, 1), "元日");
+    holidays.insert(nth_monday(year, 1, 2), "成人の日");
+    holidays.insert(ymd(2, 11), "建国記念の日");
+    if year >= 2020 {
+        holidays.insert(ymd(2, 23), "天皇誕生日");
+    } else if year <= 2018 {
+        holidays.insert(ymd(12, 23), "天皇誕生日");
+    }
+    if let Some(day) = vernal_equinox_day(year) {
+        holidays.insert(ymd(3, day), "春分の日");
+    }
+    if year >= 2007 {
+        holidays.insert(ymd(4, 29), "昭和の日");
+        holidays.insert(ymd(5, 4), "みどりの日");
+    } else {
+        holidays.insert(ymd(4, 29), "みどりの日");
+    }
+    holidays.insert(ymd(5, 3), "憲法記念日");
+    holidays.insert(ymd(5, 5), "こどもの日");
+    // 東京オリンピックの開催に伴い2020年と2021年は海の日・山の日・スポーツの日が移動
+    let marine_day = match year {
+        2020 => ymd(7, 23),
+        2021 => ymd(7, 22),
+        _ if year >= 2003 => nth_monday(year, 7, 3),
+        _ => ymd(7, 20),
+    };
+    holidays.insert(marine_day, "海の日");
+    match year {
+        2020 => holidays.insert(ymd(8, 10), "山の日"),
+        2021 => holidays.insert(ymd(8, 8), "山の日"),
+        _ if year >= 2016 => holidays.insert(ymd(8, 11), "山の日"),
+        _ => None,
+    };
+    let respect_for_the_aged_day = if year >= 2003 {
+        nth_monday(year, 9, 3)
+    } else {
+        ymd(9, 15)
+    };
+    holidays.insert(respect_for_the_aged_day, "敬老の日");
+    if let Some(day) = autumnal_equinox_day(year) {
+        holidays.insert(ymd(9, day), "秋分の日");
+    }
+    match year {
+        2020 => holidays.insert(ymd(7, 24), "スポーツの日"),
+        2021 => holidays.insert(ymd(7, 23), "スポーツの日"),
+        _ if year >= 2020 => holidays.insert(nth_monday(year, 10, 2), "スポーツの日"),
+        _ => holidays.insert(nth_monday(year, 10, 2), "体育の日"),
+    };
+    holidays.insert(ymd(11, 3), "文化の日");
+    holidays.insert(ymd(11, 23), "勤労感謝の日");
+    if year == 2019 {
+        holidays.insert(ymd(5, 1), "天皇の即位の日");
+        holidays.insert(ymd(10, 22), "即位礼正殿の儀の行われる日");
+    }
+
+    // 国民の休日: 前日と翌日が祝日である平日
+    let sandwiched: Vec<NaiveDate> = holidays
+        .keys()
+        .filter_map(|date| date.checked_add_days(Days::new(2)))
+        .filter(|date| holidays.contains_key(date))
+        .filter_map(|date| date.pred_opt())
+        .filter(|date| !holidays.contains_key(date) && date.weekday() != Weekday::Sun)
+        .collect();
+
+    // 振替休日: 日曜日の祝日の後で最も近い祝日でない日
+    let mut substitutes = vec![];
+    for date in holidays.keys().filter(|d| d.weekday() == Weekday::Sun) {
+        let mut substitute = date.succ_opt().unwrap();
+        while holidays.contains_key(&substitute) {
+            substitute = substitute.succ_opt().unwrap();
+        }
+        substitutes.push(substitute);
+    }
+
+    for date in sandwiched {
+        holidays.insert(date, "国民の休日");
+    }
+    for date in substitutes {
+        holidays.insert(date, "振替休日");
+    }
+    holidays
+}
+
+/// 指定した月の第n月曜日 (ハッピーマンデー)
+fn nth_monday(year: i32, month: u32, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Mon, n).unwrap()
+}
+
+/// 春分日 (近似式が有効な1980〜2099年以外は計算しない)
+fn vernal_equinox_day(year: i32) -> Option<u32> {
+    equinox_day(year, 20.8431)
+}
+
+/// 秋分日 (近似式が有効な1980〜2099年以外は計算しない)
+fn autumnal_equinox_day(year: i32) -> Option<u32> {
+    equinox_day(year, 23.2488)
+}
+
+fn equinox_day(year: i32, base: f64) -> Option<u32> {
+    if !(1980..=2099).contains(&year) {
+        return None;
+    }
+    let elapsed = year - 1980;
+    Some((base + 0.242194 * elapsed as f64).floor() as u32 - (elapsed / 4) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_national_holidays_2025() {
+        let holidays = HolidayCalendar::new().holidays(2025);
+        let dates: Vec<NaiveDate> = holidays.iter().map(|(d, _)| *d).collect();
+        assert_eq!(
+            dates,
+            vec![
+                date(2025, 1, 1),
+                date(2025, 1, 13),
+                date(2025, 2, 11),
+                date(2025, 2, 23),
+                date(2025, 2, 24),
+                date(2025, 3, 20),
+                date(2025, 4, 29),
+                date(2025, 5, 3),
+                date(2025, 5, 4),
+                date(2025, 5, 5),
+                date(2025, 5, 6),
+                date(2025, 7, 21),
+                date(2025, 8, 11),
+                date(2025, 9, 15),
+                date(2025, 9, 23),
+                date(2025, 10, 13),
+                date(2025, 11, 3),
+                date(2025, 11, 23),
+                date(2025, 11, 24),
+            ]
+        );
+        assert!(holidays.contains(&(date(2025, 5, 6), "振替休日".to_string())));
+    }
+
+    #[test]
+    fn test_citizens_holidays() {
+        let holidays = national_holidays(2019);
+        assert_eq!(holidays[&date(2019, 4, 30)], "国民の休日");
+        assert_eq!(holidays[&date(2019, 5, 2)], "国民の休日");
+        assert_eq!(holidays[&date(2019, 5, 6)], "振替休日");
+
+        let holidays = national_holidays(2026);
+        assert_eq!(holidays[&date(2026, 9, 22)], "国民の休日");
+    }
+
+    #[test]
+    fn test_custom_holidays() {
+        let calendar = HolidayCalendar::new().with_custom_holidays([
+            date(2025, 8, 13),
+            date(2025, 1, 1),
+            date(2024, 8, 13),
+        ]);
+        let holidays = calendar.holidays(2025);
+        assert!(holidays.contains(&(date(2025, 8, 13), "休日".to_string())));
+        assert!(holidays.contains(&(date(2025, 1, 1), "元日".to_string())));
+        assert!(!holidays.iter().any(|(d, _)| *d == date(2024, 8, 13)));
+    }
+
+    #[test]
+    fn test_holidays_outside_equinox_range() {
+        // 春分日・秋分日の近似式の範囲外の年でも他の祝日は計算できる
+        for year in [0, 1900, 2100] {
+            let holidays = national_holidays(year);
+            assert!(!holidays.values().any(|name| name.ends_with("分の日")));
+            assert_eq!(holidays[&date(year, 1, 1)], "元日");
+        }
+    }
+}
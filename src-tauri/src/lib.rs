@@ -1,19 +1,82 @@
-use std::path::PathBuf;
+mod config;
+mod csv_import;
+mod error;
+mod holiday;
+mod locale;
+mod merge;
+mod preview;
+mod progress;
+mod scale;
+mod schema;
+mod store;
+mod update;
+mod validate;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use anyhow;
 use calamine::{open_workbook, Reader, Xlsx};
 use chrono::NaiveDate;
 use polars::prelude::*;
 use polars_excel_writer::PolarsXlsxWriter;
 use rust_xlsxwriter::{
     chart::{
-        Chart, ChartFont, ChartFormat, ChartLayout, ChartLine, ChartMarker, ChartMarkerType,
-        ChartSolidFill,
+        Chart, ChartFont, ChartFormat, ChartLayout, ChartLine, ChartLineDashType, ChartMarker,
+        ChartMarkerType, ChartSeries, ChartSolidFill, ChartType,
     },
-    conditional_format::ConditionalFormatDataBar,
+    conditional_format::{ConditionalFormatDataBar, ConditionalFormatFormula},
+    utility::column_number_to_name,
     worksheet::Worksheet,
-    Color, Workbook,
+    DocProperties, Format, Workbook,
 };
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub use config::{
+    ChartLayoutConfig, ReportColors, ReportConfig, ReportSections, SheetNames, REPORT_CONFIG_FILE,
+};
+pub use csv_import::CsvImportOptions;
+pub use error::{ConditionError, ConditionResult, ErrorKind};
+pub use holiday::HolidayCalendar;
+pub use locale::Locale;
+pub use merge::{
+    merge_condition_data, merge_condition_data_with, MergeConflict, MergeOutcome, MergeStrategy,
+};
+pub use preview::{
+    preview_condition_data, ConditionCount, DataPreview, PreviewRow, PreviewSummary,
+    YearlyAggregate,
+};
+pub use progress::{CancelToken, Progress, ReportProgress};
+pub use scale::{ScoreLevel, ScoreScale};
+pub use schema::{ConditionColumn, ConditionRecord};
+pub use store::{
+    ConditionEntry, ConditionStore, EntryInput, ImportBatch, ImportKind, ImportSource,
+    RollbackSummary, STORE_FILE,
+};
+pub use validate::{validate_condition_frame, Diagnostic, DiagnosticKind};
+
+use locale::{localize_frame, localize_records};
+use merge::{assign_person, named_persons};
+use schema::{
+    localize_headers, COMMENT_COLUMN, CONDITION_COLUMN, DAILY_CHANGE_COLUMN, DATE_COLUMN,
+    DAY_OFF_COLUMN, HOLIDAY_COLUMN, LOW_STREAK_COLUMN, MONTHLY_MEAN_COLUMN, PERSON_COLUMN,
+    WEEKDAY_COLUMN, WEEKLY_MEAN_COLUMN,
+};
+use validate::{take_validated, ROW_COLUMN};
+
+/// 生データを書き出し、前回のエクセルファイルから読み込む既定のシート
+const DATA_SHEET: &str = "data";
+
+/// 人の列が空欄のデータをまとめて表示する名前
+const UNKNOWN_PERSON: &str = "未設定";
+/// レポートの見た目を決める設定のハッシュを残す文書のプロパティ
+const RENDER_FINGERPRINT_PROPERTY: &str = "renderFingerprint";
+/// エクセルの既定の行の高さと列の幅 (ピクセル、グラフと重ならない位置を求めるため)
+const DEFAULT_ROW_PIXELS: u32 = 20;
+const DEFAULT_COLUMN_PIXELS: u32 = 64;
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
 struct YearlyData {
@@ -26,106 +89,840 @@ struct MonthlyData {
     ldf: LazyFrame,
 }
 
-struct ConditionWorkbook {
+pub struct ConditionWorkbook {
     workbook: Workbook,
     writer: PolarsXlsxWriter,
+    holidays: HolidayCalendar,
+    conflicts: Vec<MergeConflict>,
+    diagnostics: Vec<Diagnostic>,
+    progress: Progress,
+    config: ReportConfig,
+    /// 作り直す年 (前回のレポートを更新する場合のみ、それ以外の年のシートは前回のものを残す)
+    years: Option<Vec<i32>>,
+    /// 作成済みの年毎のシートの数と全体の数 (進み具合の通知用)
+    sheet_count: (usize, usize),
+}
+
+impl Default for ConditionWorkbook {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl ConditionWorkbook {
-    fn new() -> Self {
+    pub fn new() -> Self {
         let workbook = Workbook::new();
         let writer = PolarsXlsxWriter::new();
-        Self { workbook, writer }
+        let holidays = HolidayCalendar::new();
+        Self {
+            workbook,
+            writer,
+            holidays,
+            conflicts: vec![],
+            diagnostics: vec![],
+            progress: Progress::default(),
+            config: ReportConfig::default(),
+            years: None,
+            sheet_count: (0, 0),
+        }
+    }
+
+    /// 年毎のシートで土日と同様に扱う休日を設定する
+    pub fn set_holidays(&mut self, holidays: HolidayCalendar) -> &mut Self {
+        self.holidays = holidays;
+        self
     }
 
-    fn write(&mut self, ldf: &LazyFrame, path: &str) -> anyhow::Result<()> {
-        match self._write_raw_data(ldf, "data") {
-            Ok(_) => self._write_yearly_data(ldf)?,
+    /// 結合時に値が異なっていた項目をconflictsシートに書き出すよう設定する
+    pub fn set_conflicts(&mut self, conflicts: Vec<MergeConflict>) -> &mut Self {
+        self.conflicts = conflicts;
+        self
+    }
+
+    /// 読み込み時の検証結果を検証結果シートに書き出すよう設定する
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) -> &mut Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// 年毎のシートの作成と保存の進み具合を通知し、中断の指示を確認する
+    pub fn set_progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+
+    /// シート名・グラフの配置・色・出力するシートを設定する
+    pub fn set_config(&mut self, mut config: ReportConfig) -> &mut Self {
+        config.sheet_names = config.sheet_names.localized(config.locale);
+        self.config = config;
+        self
+    }
+
+    /// 生データと年毎の集計シート・グラフを含むレポートを保存する
+    pub fn write(&mut self, ldf: &LazyFrame, path: impl AsRef<Path>) -> ConditionResult<()> {
+        self.sheet_count = (0, count_yearly_sheets(ldf, self.years.as_deref())?);
+        let names = self.config.sheet_names.clone();
+        let sections = self.config.sections.clone();
+        match self._write_raw_data(ldf, &names.data) {
+            Ok(_) => match person_frames(ldf)? {
+                // 複数人のデータは人毎に年のシートを作り、人別比較シートで並べる
+                Some(person_frames) => {
+                    for (person, person_ldf) in &person_frames {
+                        let person = self._person_label(person);
+                        self._write_yearly_data(person_ldf, Some(person))?;
+                        if sections.weekday_analysis {
+                            self._write_weekday_analysis(person_ldf, Some(person))?;
+                        }
+                        if sections.missing_days {
+                            self._write_missing_days(person_ldf, Some(person))?;
+                        }
+                    }
+                    if sections.person_comparison {
+                        self._write_person_comparison(&fill_unknown_person(ldf))?;
+                    }
+                }
+                None => {
+                    self._write_yearly_data(ldf, None)?;
+                    if sections.weekday_analysis {
+                        self._write_weekday_analysis(ldf, None)?;
+                    }
+                    if sections.missing_days {
+                        self._write_missing_days(ldf, None)?;
+                    }
+                }
+            },
             Err(e) => return Err(e),
         }
+        self._write_conflicts(&names.conflicts)?;
+        self._write_diagnostics(&names.diagnostics)?;
+        self._save(path.as_ref())
+    }
+
+    /// 前回のレポートを元に、データが増えたり変わったりした年のシートだけを作り直して保存する
+    ///
+    /// dataシートと集計・比較のシートは作り直し、それ以外の年のシートや利用者が追加したシートは
+    /// 書式やメモを含めて前回のレポートから移す。保存先は前回のレポートと同じでもよい。
+    /// 前回のレポートと設定や休日が異なる場合は、全ての年のシートを作り直す。
+    pub fn update(
+        &mut self,
+        ldf: &LazyFrame,
+        previous: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+    ) -> ConditionResult<UpdateSummary> {
+        let (previous, path) = (previous.as_ref(), path.as_ref());
+        let data_sheet = self.config.sheet_names.data.clone();
+        let previous_df =
+            read_excel_with_rows(previous, &data_sheet, &mut vec![])?.drop(ROW_COLUMN)?;
+        let mut years = update::changed_years(&previous_df, &ldf.clone().collect()?)?;
+        // 前回のレポートに年毎のシートが無い年も作り直す (dataシートのみのファイルやシートを消した場合)
+        let excel: Xlsx<_> =
+            open_workbook(previous).map_err(|e| ConditionError::excel_open(previous, e))?;
+        let previous_sheets = excel.sheet_names();
+        let yearly_sheets = self._yearly_sheet_names(ldf)?;
+        // 言語・色・休日などの設定が前回と違えば、全ての年を作り直す
+        let fingerprint = update::custom_property(previous, RENDER_FINGERPRINT_PROPERTY)?;
+        let restyled = fingerprint != Some(self._render_fingerprint()?);
+        for (year, name) in &yearly_sheets {
+            if (restyled || !previous_sheets.contains(name)) && !years.contains(year) {
+                years.push(*year);
+            }
+        }
+        years.sort_unstable();
+        self.years = Some(years.clone());
+
+        // 作り直したシートを一時ファイルに保存してから、前回のレポートのシートを移す
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let generated = tempfile::Builder::new()
+            .suffix(".xlsx")
+            .tempfile_in(dir)
+            .map_err(|e| ConditionError::save(path, e))?;
+        self.write(ldf, generated.path())?;
+        // 作り直さなかった年のシートと利用者が追加したシートだけを移し、
+        // 今回は出力しなかったconflictsシートや前回の言語のシートなどは残さない
+        let mut generated_bases: Vec<String> = self
+            .config
+            .sheet_names
+            .all()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for locale in [Locale::Ja, Locale::En] {
+            let names = SheetNames::default().localized(locale);
+            generated_bases.extend(names.all().into_iter().map(String::from));
+        }
+        let keep = |name: &str| match yearly_sheets.iter().find(|(_, sheet)| sheet == name) {
+            Some((year, _)) => !years.contains(year),
+            None => !is_generated_sheet_name(name, &generated_bases),
+        };
+        let carried_sheets = update::carry_over_sheets(previous, generated.path(), path, keep)?;
+        Ok(UpdateSummary {
+            years,
+            carried_sheets,
+        })
+    }
+
+    /// 年毎のシートの年と名前 (複数人のデータでは人毎のシート)
+    fn _yearly_sheet_names(&self, ldf: &LazyFrame) -> ConditionResult<Vec<(i32, String)>> {
+        let mut names = vec![];
+        match person_frames(ldf)? {
+            Some(person_frames) => {
+                for (person, person_ldf) in &person_frames {
+                    for yearly_data in extract_yearly_frame_vec(person_ldf)? {
+                        let name = person_sheet_name(
+                            &yearly_data.year.to_string(),
+                            Some(self._person_label(person)),
+                        );
+                        names.push((yearly_data.year, name));
+                    }
+                }
+            }
+            None => {
+                for yearly_data in extract_yearly_frame_vec(ldf)? {
+                    names.push((yearly_data.year, yearly_data.year.to_string()));
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// シート名に使う人の名前 (人が空欄のデータはレポートの言語の「未設定」)
+    fn _person_label<'a>(&self, person: &'a str) -> &'a str {
+        match person {
+            UNKNOWN_PERSON => self.config.locale.text(UNKNOWN_PERSON),
+            person => person,
+        }
+    }
+
+    /// 結合済みの生データ(dataシート)のみを保存する
+    pub fn write_data(&mut self, ldf: &LazyFrame, path: impl AsRef<Path>) -> ConditionResult<()> {
+        let names = self.config.sheet_names.clone();
+        self._write_raw_data(ldf, &names.data)?;
+        self._write_conflicts(&names.conflicts)?;
+        self._write_diagnostics(&names.diagnostics)?;
+        self._save(path.as_ref())
+    }
+
+    /// 年毎のシートの見た目を決める設定(言語・色・グラフの配置・休日など)のハッシュ
+    fn _render_fingerprint(&self) -> ConditionResult<String> {
+        let inputs = serde_json::to_vec(&(&self.config, self.holidays.custom_holidays()))
+            .map_err(|e| ConditionError::Internal(e.to_string()))?;
+        Ok(Sha256::digest(&inputs)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+
+    fn _save(&mut self, path: &Path) -> ConditionResult<()> {
+        self.progress.report(ReportProgress::Saving)?;
+        let properties = DocProperties::new()
+            .set_custom_property(RENDER_FINGERPRINT_PROPERTY, self._render_fingerprint()?);
+        self.workbook.set_properties(&properties);
         match self.workbook.save(path) {
             Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
+            Err(e) => Err(ConditionError::save(path, e)),
         }
     }
 
-    fn _write_raw_data(&mut self, ldf: &LazyFrame, sheet_name: &str) -> anyhow::Result<()> {
+    fn _write_raw_data(&mut self, ldf: &LazyFrame, sheet_name: &str) -> ConditionResult<()> {
+        let worksheet = self.workbook.add_worksheet().set_name(sheet_name)?;
+        let df = localize_headers(&ldf.clone().collect()?, self.config.locale)?;
+        self.writer
+            .write_dataframe_to_worksheet(&df, worksheet, 0, 0)?;
+        Ok(())
+    }
+
+    fn _write_conflicts(&mut self, sheet_name: &str) -> ConditionResult<()> {
+        if self.conflicts.is_empty() {
+            return Ok(());
+        }
+        let mut conflicts_df = df!(
+            DATE_COLUMN => self.conflicts.iter().map(|c| c.date).collect::<Vec<_>>(),
+            "列" => self.conflicts.iter().map(|c| c.column.clone()).collect::<Vec<_>>(),
+            "結合前" => self.conflicts.iter().map(|c| c.excel.clone()).collect::<Vec<_>>(),
+            "CSV" => self.conflicts.iter().map(|c| c.csv.clone()).collect::<Vec<_>>(),
+            "採用した値" => self.conflicts.iter().map(|c| c.resolved.clone()).collect::<Vec<_>>(),
+        )?;
+        if self.conflicts.iter().any(|c| c.person.is_some()) {
+            let persons: Vec<Option<String>> =
+                self.conflicts.iter().map(|c| c.person.clone()).collect();
+            conflicts_df.insert_column(1, Series::new(PERSON_COLUMN.into(), persons))?;
+        }
+        let conflicts_df = localize_frame(&conflicts_df, self.config.locale)?;
         let worksheet = self.workbook.add_worksheet().set_name(sheet_name)?;
+        self.writer
+            .write_dataframe_to_worksheet(&conflicts_df, worksheet, 0, 0)?;
+        Ok(())
+    }
 
+    fn _write_diagnostics(&mut self, sheet_name: &str) -> ConditionResult<()> {
+        if self.diagnostics.is_empty() {
+            return Ok(());
+        }
+        let diagnostics_df = df!(
+            "ファイル" => self
+                .diagnostics
+                .iter()
+                .map(|d| d.path.file_name().map(|f| f.to_string_lossy().to_string()))
+                .collect::<Vec<_>>(),
+            "行" => self.diagnostics.iter().map(|d| d.row.map(|r| r as u32)).collect::<Vec<_>>(),
+            DATE_COLUMN => self.diagnostics.iter().map(|d| d.date).collect::<Vec<_>>(),
+            "列" => self.diagnostics.iter().map(|d| d.column.clone()).collect::<Vec<_>>(),
+            "種類" => self.diagnostics.iter().map(|d| d.kind.label()).collect::<Vec<_>>(),
+            "値" => self.diagnostics.iter().map(|d| d.value.clone()).collect::<Vec<_>>(),
+        )?;
+        let diagnostics_df = localize_frame(&diagnostics_df, self.config.locale)?;
+        let worksheet = self.workbook.add_worksheet().set_name(sheet_name)?;
         self.writer
-            .write_dataframe_to_worksheet(&ldf.clone().collect()?, worksheet, 0, 0)?;
+            .write_dataframe_to_worksheet(&diagnostics_df, worksheet, 0, 0)?;
         Ok(())
     }
 
-    fn _write_yearly_data(&mut self, ldf: &LazyFrame) -> anyhow::Result<()> {
+    fn _write_yearly_data(&mut self, ldf: &LazyFrame, person: Option<&str>) -> ConditionResult<()> {
         // # 年間の体調集計データの比較シートを作成
-        let comp_sheet_name = "年間体調比較";
+        let comp_sheet_name = &person_sheet_name(&self.config.sheet_names.comparison, person);
         self.workbook.add_worksheet().set_name(comp_sheet_name)?;
 
         // 集計表用の書式を設定
-        let annual_data_format = ConditionalFormatDataBar::new().set_fill_color(Color::Orange);
-        let monthly_data_format = ConditionalFormatDataBar::new().set_fill_color(Color::Green);
+        let colors = &self.config.colors;
+        let annual_data_format =
+            ConditionalFormatDataBar::new().set_fill_color(colors.annual_bar.as_str());
+        let monthly_data_format =
+            ConditionalFormatDataBar::new().set_fill_color(colors.monthly_bar.as_str());
 
         let mut row = 0;
         let col = 0;
-        // Create a new Excel writer.
-        for yearly_data in extract_yearly_frame_vec(ldf) {
-            let sheet_name = yearly_data.year.to_string();
-            let mut worksheet = Worksheet::new();
-            worksheet.set_name(&sheet_name)?;
-            // この年のシートにデータを書き込み
-            let yearly_ldf = prepare_yearly_frame(&yearly_data.ldf, yearly_data.year);
-            let yearly_df = match yearly_ldf.clone().collect() {
-                Ok(df) => df,
-                Err(e) => return Err(e.into()),
-            };
-            self.writer
-                .write_dataframe_to_worksheet(&yearly_df, &mut worksheet, 0, 0)?;
-
-            let yearly_agg_df = prepare_agg_frame(&yearly_ldf).collect()?;
-            self.writer
-                .write_dataframe_to_worksheet(&yearly_agg_df, &mut worksheet, 0, 6)?;
-
-            // 集計表に条件付き書式を設定
-            worksheet.add_conditional_format(1, 8, 6, 8, &annual_data_format.clone())?;
-            worksheet.add_conditional_format(1, 9, 6, 20, &monthly_data_format.clone())?;
+        let mut yearly_agg_dfs = vec![];
+        for yearly_data in extract_yearly_frame_vec(ldf)? {
+            let sheet_name = person_sheet_name(&yearly_data.year.to_string(), person);
+            let yearly_ldf = prepare_yearly_frame(
+                &yearly_data.ldf,
+                yearly_data.year,
+                &self.holidays,
+                &self.config.scale,
+            )?;
+            let yearly_agg_df = prepare_agg_table(&yearly_ldf, &self.config.scale)?.collect()?;
+            // 更新時はデータが変わった年のシートだけを作り直す
+            let rewrite = self
+                .years
+                .as_ref()
+                .is_none_or(|years| years.contains(&yearly_data.year));
+            if rewrite {
+                self.sheet_count.0 += 1;
+                self.progress.report(ReportProgress::Sheet {
+                    year: yearly_data.year,
+                    person: person.map(String::from),
+                    index: self.sheet_count.0,
+                    total: self.sheet_count.1,
+                })?;
+                self._write_year_sheet(&sheet_name, &yearly_ldf, &yearly_agg_df)?;
+            }
 
             // 年毎体調比較シートに集計データを書き込み
             let workbook_comp = self.workbook.worksheet_from_name(comp_sheet_name)?;
             workbook_comp.write_string(row, col, &sheet_name)?;
             row += 1;
-            self.writer
-                .write_dataframe_to_worksheet(&yearly_agg_df, workbook_comp, row, col)?;
+            self.writer.write_dataframe_to_worksheet(
+                &localize_frame(&yearly_agg_df, self.config.locale)?,
+                workbook_comp,
+                row,
+                col,
+            )?;
             let end_row = row + yearly_agg_df.height() as u32;
             // 集計表に条件付き書式を設定
-            workbook_comp.add_conditional_format(row + 1, 2, end_row, 2, &annual_data_format)?;
-            workbook_comp.add_conditional_format(row + 1, 3, end_row, 14, &monthly_data_format)?;
+            let count_end_row = row + self.config.scale.levels.len() as u32;
+            workbook_comp.add_conditional_format(
+                row + 1,
+                2,
+                count_end_row,
+                2,
+                &annual_data_format,
+            )?;
+            workbook_comp.add_conditional_format(
+                row + 1,
+                3,
+                count_end_row,
+                14,
+                &monthly_data_format,
+            )?;
             row = end_row + 1;
 
-            // この年のシートに月毎の体調推移グラフを挿入
-            self._insert_monthly_trend_chart(&mut worksheet, &yearly_ldf)?;
-            // workbookにworksheetを追加
-            self.workbook.push_worksheet(worksheet);
+            yearly_agg_dfs.push((yearly_data.year, yearly_agg_df));
+        }
+        // 集計表の右(16列目以降)に年の比較用の表とグラフを配置する
+        yearly_agg_dfs.sort_by_key(|(year, _)| *year);
+        if self.config.sections.year_comparison_charts {
+            self._insert_year_comparison_charts(comp_sheet_name, &yearly_agg_dfs, 16)?;
+        }
+        Ok(())
+    }
+
+    /// 年毎のシートに日毎のデータ・集計表・月毎のグラフを書き込む
+    fn _write_year_sheet(
+        &mut self,
+        sheet_name: &str,
+        yearly_ldf: &LazyFrame,
+        yearly_agg_df: &DataFrame,
+    ) -> ConditionResult<()> {
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(sheet_name)?;
+        // この年のシートにデータを書き込み
+        let yearly_df = yearly_ldf.clone().collect()?;
+        self.writer.write_dataframe_to_worksheet(
+            &localize_records(&yearly_df, self.config.locale)?,
+            &mut worksheet,
+            0,
+            0,
+        )?;
+        if self.config.sections.highlight_missing_days {
+            self._highlight_missing_days(&mut worksheet, &yearly_df)?;
+        }
+
+        // 集計表は日毎のデータの右に1列空けて配置する
+        let agg_col = yearly_df.width() as u16 + 1;
+        self.writer.write_dataframe_to_worksheet(
+            &localize_frame(yearly_agg_df, self.config.locale)?,
+            &mut worksheet,
+            0,
+            agg_col,
+        )?;
+
+        // 集計表の日数の行に条件付き書式を設定 (統計の行は除く)
+        let colors = &self.config.colors;
+        let count_rows = self.config.scale.levels.len() as u32;
+        worksheet.add_conditional_format(
+            1,
+            agg_col + 2,
+            count_rows,
+            agg_col + 2,
+            &ConditionalFormatDataBar::new().set_fill_color(colors.annual_bar.as_str()),
+        )?;
+        worksheet.add_conditional_format(
+            1,
+            agg_col + 3,
+            count_rows,
+            agg_col + 14,
+            &ConditionalFormatDataBar::new().set_fill_color(colors.monthly_bar.as_str()),
+        )?;
+
+        // この年のシートに月毎の体調推移グラフを挿入
+        if self.config.sections.monthly_charts {
+            self._insert_monthly_trend_chart(&mut worksheet, &yearly_df, agg_col - 1)?;
+        }
+        // workbookにworksheetを追加
+        self.workbook.push_worksheet(worksheet);
+        Ok(())
+    }
+
+    fn _insert_year_comparison_charts(
+        &mut self,
+        sheet_name: &str,
+        yearly_agg_dfs: &[(i32, DataFrame)],
+        insert_col: u16,
+    ) -> ConditionResult<()> {
+        if yearly_agg_dfs.is_empty() {
+            return Ok(());
+        }
+        // 集計表の平均の行から、月毎の平均を年毎の列に並べる
+        let scale = self.config.scale.clone();
+        let mean_row = scale.levels.len();
+        let mut mean_columns = vec![Column::new(
+            "月".into(),
+            (1..=12)
+                .map(|month| format!("{month}月"))
+                .collect::<Vec<_>>(),
+        )];
+        for (year, agg_df) in yearly_agg_dfs {
+            let means = (1..=12)
+                .map(|month| {
+                    let column = agg_df
+                        .column(&format!("{month}月"))?
+                        .cast(&DataType::Float64)?;
+                    Ok(column.f64()?.get(mean_row))
+                })
+                .collect::<PolarsResult<Vec<_>>>()?;
+            mean_columns.push(Column::new(format!("{year}年").into(), means));
+        }
+        let mean_df = DataFrame::new(mean_columns)?;
+
+        // 年毎の体調の日数を、体調毎の列に並べる
+        let mut distribution_columns = vec![Column::new(
+            "年".into(),
+            yearly_agg_dfs
+                .iter()
+                .map(|(year, _)| format!("{year}年"))
+                .collect::<Vec<_>>(),
+        )];
+        for (i, level) in scale.levels.iter().enumerate() {
+            let counts = yearly_agg_dfs
+                .iter()
+                .map(|(_, agg_df)| {
+                    let column = agg_df.column("年間")?.cast(&DataType::Float64)?;
+                    Ok(column.f64()?.get(i))
+                })
+                .collect::<PolarsResult<Vec<_>>>()?;
+            distribution_columns.push(Column::new(level.column_name().into(), counts));
+        }
+        let distribution_df = DataFrame::new(distribution_columns)?;
+
+        let distribution_col = insert_col + mean_df.width() as u16 + 1;
+        // 月平均の年比較 (折れ線と縦棒)
+        let mut line_chart = Chart::new_line();
+        let mut column_chart = Chart::new_column();
+        for i in 1..mean_df.width() as u16 {
+            self._add_series(
+                &mut line_chart,
+                sheet_name,
+                1,
+                insert_col,
+                12,
+                insert_col + i,
+            )
+            .set_marker(ChartMarker::new().set_type(ChartMarkerType::Circle));
+            self._add_series(
+                &mut column_chart,
+                sheet_name,
+                1,
+                insert_col,
+                12,
+                insert_col + i,
+            );
+        }
+        // 体調毎の日数の年比較 (積み上げ縦棒)
+        let mut distribution_chart = Chart::new(ChartType::ColumnStacked);
+        let end_row = yearly_agg_dfs.len() as u32;
+        for (i, level) in scale.levels.iter().enumerate() {
+            let series = self
+                ._add_series(
+                    &mut distribution_chart,
+                    sheet_name,
+                    1,
+                    distribution_col,
+                    end_row,
+                    distribution_col + 1 + i as u16,
+                )
+                .set_gap(50);
+            if let Some(color) = &level.color {
+                series.set_format(
+                    ChartFormat::new()
+                        .set_solid_fill(ChartSolidFill::new().set_color(color.as_str())),
+                );
+            }
+        }
+
+        let locale = self.config.locale;
+        let worksheet = self.workbook.worksheet_from_name(sheet_name)?;
+        self.writer.write_dataframe_to_worksheet(
+            &localize_frame(&mean_df, locale)?,
+            worksheet,
+            0,
+            insert_col,
+        )?;
+        self.writer.write_dataframe_to_worksheet(
+            &localize_frame(&distribution_df, locale)?,
+            worksheet,
+            0,
+            distribution_col,
+        )?;
+
+        // (グラフ, タイトル, 挿入する行, 列, 体調の値の軸か)
+        let layout = &self.config.chart_layout;
+        let next_row = 14 + layout.summary_height.div_ceil(DEFAULT_ROW_PIXELS) + 2;
+        let next_col =
+            insert_col + (layout.summary_width.div_ceil(DEFAULT_COLUMN_PIXELS) + 1) as u16;
+        let charts = [
+            (line_chart, "月平均の体調 (折れ線)", 14, insert_col, true),
+            (column_chart, "月平均の体調 (縦棒)", 14, next_col, true),
+            (
+                distribution_chart,
+                "年間の体調の分布",
+                next_row,
+                insert_col,
+                false,
+            ),
+        ];
+        for (mut chart, title, insert_row, insert_col, condition_axis) in charts {
+            chart
+                .title()
+                .set_name(locale.text(title))
+                .set_font(ChartFont::new().set_size(14));
+            chart.set_width(layout.summary_width);
+            chart.set_height(layout.summary_height);
+            if condition_axis {
+                chart
+                    .y_axis()
+                    .set_min(scale.axis_min())
+                    .set_max(scale.max)
+                    .set_major_unit(1);
+            }
+            worksheet.insert_chart(insert_row, insert_col, &chart)?;
+        }
+        Ok(())
+    }
+
+    fn _write_weekday_analysis(
+        &mut self,
+        ldf: &LazyFrame,
+        person: Option<&str>,
+    ) -> ConditionResult<()> {
+        // 曜日・土日祝毎の体調を年毎と全期間で並べる
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(person_sheet_name(
+            &self.config.sheet_names.weekday_analysis,
+            person,
+        ))?;
+        let colors = &self.config.colors;
+        let mean_format =
+            ConditionalFormatDataBar::new().set_fill_color(colors.annual_bar.as_str());
+        let count_format =
+            ConditionalFormatDataBar::new().set_fill_color(colors.monthly_bar.as_str());
+
+        let mut sections = vec![];
+        let mut yearly_ldfs = vec![];
+        for yearly_data in extract_yearly_frame_vec(ldf)? {
+            let yearly_ldf = prepare_yearly_frame(
+                &yearly_data.ldf,
+                yearly_data.year,
+                &self.holidays,
+                &self.config.scale,
+            )?;
+            sections.push((yearly_data.year, yearly_ldf.clone()));
+            yearly_ldfs.push(yearly_ldf);
+        }
+        sections.sort_by_key(|(year, _)| *year);
+        let locale = self.config.locale;
+        let mut sections: Vec<(String, LazyFrame)> = sections
+            .into_iter()
+            .map(|(year, yearly_ldf)| (locale.year(year), yearly_ldf))
+            .collect();
+        if sections.len() > 1 {
+            sections.push((
+                locale.text("全期間").into(),
+                concat(yearly_ldfs, UnionArgs::default())?,
+            ));
+        }
+
+        let mut row = 0;
+        for (title, yearly_ldf) in sections {
+            worksheet.write_string(row, 0, title)?;
+            let header_row = row + 1;
+            let weekday_df = prepare_weekday_frame(&yearly_ldf, &self.config.scale)?.collect()?;
+            self.writer.write_dataframe_to_worksheet(
+                &localize_frame(&weekday_df, locale)?,
+                &mut worksheet,
+                header_row,
+                0,
+            )?;
+            let end_row = header_row + weekday_df.height() as u32;
+            // 列: 0=区分, 1=平均, 2=中央値, 3=記録日数, 4〜=体調毎の日数
+            let last_col = weekday_df.width() as u16 - 1;
+            worksheet.add_conditional_format(header_row + 1, 1, end_row, 1, &mean_format)?;
+            worksheet.add_conditional_format(
+                header_row + 1,
+                4,
+                end_row,
+                last_col,
+                &count_format,
+            )?;
+            row = end_row + 2;
+        }
+        self.workbook.push_worksheet(worksheet);
+        Ok(())
+    }
+
+    fn _write_missing_days(
+        &mut self,
+        ldf: &LazyFrame,
+        person: Option<&str>,
+    ) -> ConditionResult<()> {
+        // 年毎に月毎の記録率と未記録の期間を並べる
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(person_sheet_name(
+            &self.config.sheet_names.missing_days,
+            person,
+        ))?;
+        let rate_format =
+            ConditionalFormatDataBar::new().set_fill_color(self.config.colors.monthly_bar.as_str());
+
+        let mut yearly_data_vec = extract_yearly_frame_vec(ldf)?;
+        yearly_data_vec.sort_by_key(|yearly_data| yearly_data.year);
+        let mut row = 0;
+        for yearly_data in yearly_data_vec {
+            let yearly_ldf = prepare_yearly_frame(
+                &yearly_data.ldf,
+                yearly_data.year,
+                &self.holidays,
+                &self.config.scale,
+            )?;
+            worksheet.write_string(row, 0, self.config.locale.year(yearly_data.year))?;
+            let header_row = row + 1;
+            let completeness_df = prepare_completeness_frame(&yearly_ldf)?.collect()?;
+            self.writer.write_dataframe_to_worksheet(
+                &localize_frame(&completeness_df, self.config.locale)?,
+                &mut worksheet,
+                header_row,
+                0,
+            )?;
+            // 列: 0=月, 1=日数, 2=記録日数, 3=未記録日数, 4=記録率
+            let completeness_end_row = header_row + completeness_df.height() as u32;
+            worksheet.add_conditional_format(
+                header_row + 1,
+                4,
+                completeness_end_row,
+                4,
+                &rate_format,
+            )?;
+
+            // 未記録の期間は記録率の表の右に1列空けて配置する
+            let ranges_col = completeness_df.width() as u16 + 1;
+            let ranges_df = prepare_missing_ranges_frame(&yearly_ldf).collect()?;
+            self.writer.write_dataframe_to_worksheet(
+                &localize_frame(&ranges_df, self.config.locale)?,
+                &mut worksheet,
+                header_row,
+                ranges_col,
+            )?;
+            let ranges_end_row = header_row + ranges_df.height() as u32;
+            row = completeness_end_row.max(ranges_end_row) + 2;
+        }
+        self.workbook.push_worksheet(worksheet);
+        Ok(())
+    }
+
+    /// 年毎のシートで、最後の記録までの体調が空の日の行に色を付ける
+    fn _highlight_missing_days(
+        &self,
+        worksheet: &mut Worksheet,
+        yearly_df: &DataFrame,
+    ) -> ConditionResult<()> {
+        let condition = yearly_df.column(CONDITION_COLUMN)?;
+        let last_recorded = condition
+            .is_not_null()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, recorded)| recorded.unwrap_or(false).then_some(i))
+            .last();
+        let (Some(last_recorded), Some(condition_col)) =
+            (last_recorded, yearly_df.get_column_index(CONDITION_COLUMN))
+        else {
+            return Ok(());
+        };
+        // 1行目は見出し
+        let missing_rule = format!("=${}2=\"\"", column_number_to_name(condition_col as u16));
+        let missing_format = ConditionalFormatFormula::new()
+            .set_rule(missing_rule.as_str())
+            .set_format(
+                Format::new().set_background_color(self.config.colors.missing_fill.as_str()),
+            );
+        worksheet.add_conditional_format(
+            1,
+            0,
+            last_recorded as u32 + 1,
+            yearly_df.width() as u16 - 1,
+            &missing_format,
+        )?;
+        Ok(())
+    }
+
+    fn _write_person_comparison(&mut self, ldf: &LazyFrame) -> ConditionResult<()> {
+        // 人毎の月平均の体調を年毎に並べる
+        let sheet_name = self.config.sheet_names.person_comparison.clone();
+        let sheet_name = sheet_name.as_str();
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(sheet_name)?;
+
+        let mut row = 0;
+        for yearly_data in extract_yearly_frame_vec(ldf)? {
+            let mut agg_exprs = vec![col(CONDITION_COLUMN).mean().alias("年間")];
+            for month in 1..=12 {
+                agg_exprs.push(
+                    col(CONDITION_COLUMN)
+                        .filter(col(DATE_COLUMN).dt().month().eq(lit(month)))
+                        .mean()
+                        .alias(format!("{month}月")),
+                );
+            }
+            let person_df = yearly_data
+                .ldf
+                .group_by([col(PERSON_COLUMN)])
+                .agg(agg_exprs)
+                .sort([PERSON_COLUMN], SortMultipleOptions::default())
+                .collect()?;
+
+            let locale = self.config.locale;
+            worksheet.write_string(row, 0, locale.year(yearly_data.year))?;
+            let header_row = row + 1;
+            self.writer.write_dataframe_to_worksheet(
+                &localize_frame(&person_df, locale)?,
+                &mut worksheet,
+                header_row,
+                0,
+            )?;
+            let end_row = header_row + person_df.height() as u32;
+
+            // 人毎の月平均の推移グラフ (列: 0=人, 1=年間, 2〜13=1〜12月)
+            let mut chart = Chart::new_line();
+            for person_row in header_row + 1..=end_row {
+                chart
+                    .add_series()
+                    .set_name((sheet_name, person_row, 0))
+                    .set_categories((sheet_name, header_row, 2, header_row, 13))
+                    .set_values((sheet_name, person_row, 2, person_row, 13))
+                    .set_marker(ChartMarker::new().set_type(ChartMarkerType::Circle));
+            }
+            chart
+                .title()
+                .set_name(&format!(
+                    "{} {}",
+                    locale.year(yearly_data.year),
+                    locale.text("月平均の体調")
+                ))
+                .set_font(&ChartFont::new().set_size(14));
+            chart.set_width(self.config.chart_layout.summary_width);
+            chart.set_height(self.config.chart_layout.summary_height);
+            chart
+                .y_axis()
+                .set_min(self.config.scale.axis_min())
+                .set_max(self.config.scale.max)
+                .set_major_unit(1);
+            worksheet.insert_chart(header_row, 15, &chart)?;
+
+            // グラフと重ならないように次の年を配置する
+            let chart_rows = self
+                .config
+                .chart_layout
+                .summary_height
+                .div_ceil(DEFAULT_ROW_PIXELS);
+            row = end_row.max(header_row + chart_rows) + 2;
         }
+        self.workbook.push_worksheet(worksheet);
         Ok(())
     }
 
     fn _insert_monthly_trend_chart(
         &self,
         worksheet: &mut Worksheet,
-        yearly_ldf: &LazyFrame,
-    ) -> anyhow::Result<()> {
+        yearly_df: &DataFrame,
+        insert_col: u16,
+    ) -> ConditionResult<()> {
         // 体調の推移グラフ挿入
 
-        let insert_matrix = (6, 2); // 6行2列に並べる
-        let insert_start_cell = (8, 5); // F8セルから挿入
-        let per_chart_offset = (8, 11); // グラフの配置間隔がセルで何個分か
-        let yearly_ldf_wt_idx = yearly_ldf.clone().with_row_index("cell_row", Some(1));
+        let layout = &self.config.chart_layout;
+        let insert_matrix = (12_usize.div_ceil(layout.columns), layout.columns); // 既定では6行2列に並べる
+        let insert_start_cell = (layout.start_row, insert_col as usize); // 既定では集計表の下の13行目から挿入
+        let per_chart_offset = (layout.row_offset, layout.column_offset); // グラフの配置間隔がセルで何個分か
+        let condition_col = yearly_df.try_get_column_index(CONDITION_COLUMN)? as u16;
+        let day_off_col = yearly_df.try_get_column_index(DAY_OFF_COLUMN)? as u16;
+        let rolling_mean_col = yearly_df.get_column_index(WEEKLY_MEAN_COLUMN);
+        let yearly_ldf_wt_idx = yearly_df.clone().lazy().with_row_index("cell_row", Some(1));
 
-        for (i, monthly_data) in extract_monthly_frame_vec(&yearly_ldf_wt_idx)
+        for (i, monthly_data) in extract_monthly_frame_vec(&yearly_ldf_wt_idx)?
             .iter()
             .enumerate()
         {
-            let jp_month_str = format!("{}月", monthly_data.month);
+            let month_title = self.config.locale.month_title(monthly_data.month);
 
             // 月毎の体調トレンドのグラフ作成
             let monthly_data_df = monthly_data.ldf.clone().collect()?;
@@ -135,18 +932,38 @@ impl ConditionWorkbook {
             let end_row = cell_rows.last().unwrap();
             let start_col = 0;
 
-            let trend_line_chart =
-                self._add_line_chart(&*worksheet.name(), start_row, start_col, end_row, 1);
+            let mut trend_line_chart = self._add_line_chart(
+                &*worksheet.name(),
+                start_row,
+                start_col,
+                end_row,
+                condition_col,
+            );
+            if let Some(value_col) = rolling_mean_col {
+                self._add_rolling_mean_series(
+                    &mut trend_line_chart,
+                    &worksheet.name(),
+                    start_row,
+                    start_col,
+                    end_row,
+                    value_col as u16,
+                );
+            }
 
-            let mut base_chart =
-                self._add_base_chart(&*worksheet.name(), start_row, start_col, end_row, 4);
+            let mut base_chart = self._add_base_chart(
+                &*worksheet.name(),
+                start_row,
+                start_col,
+                end_row,
+                day_off_col,
+            );
 
             // グラフの結合
             base_chart.combine(&trend_line_chart);
             // 書式調整
             base_chart
                 .title()
-                .set_name(&jp_month_str)
+                .set_name(&month_title)
                 .set_font(&ChartFont::new().set_size(14));
             self._set_chart_format(&mut base_chart, monthly_data_df.height() as u32)?;
 
@@ -168,14 +985,59 @@ impl ConditionWorkbook {
     ) -> Chart {
         let mut line_chart = Chart::new_line();
 
+        self._add_series(
+            &mut line_chart,
+            sheet_name,
+            start_row,
+            start_col,
+            end_row,
+            end_col,
+        )
+        .set_format(
+            ChartFormat::new()
+                .set_line(ChartLine::new().set_color(self.config.colors.trend_line.as_str())),
+        )
+        .set_marker(ChartMarker::new().set_type(ChartMarkerType::Circle));
         line_chart
+    }
+
+    /// 1行目を系列名、start_colの列を項目とした系列を追加する
+    fn _add_series<'a>(
+        &self,
+        chart: &'a mut Chart,
+        sheet_name: &str,
+        start_row: u32,
+        start_col: u16,
+        end_row: u32,
+        value_col: u16,
+    ) -> &'a mut ChartSeries {
+        chart
             .add_series()
-            .set_name((sheet_name, 0, end_col))
+            .set_name((sheet_name, 0, value_col))
             .set_categories((sheet_name, start_row, start_col, end_row, start_col))
-            .set_values((sheet_name, start_row, end_col, end_row, end_col))
-            .set_format(ChartFormat::new().set_line(ChartLine::new().set_color(Color::Blue)))
-            .set_marker(ChartMarker::new().set_type(ChartMarkerType::Circle));
-        line_chart
+            .set_values((sheet_name, start_row, value_col, end_row, value_col))
+    }
+
+    /// 移動平均の推移を点線で重ねる
+    fn _add_rolling_mean_series(
+        &self,
+        line_chart: &mut Chart,
+        sheet_name: &str,
+        start_row: u32,
+        start_col: u16,
+        end_row: u32,
+        value_col: u16,
+    ) {
+        self._add_series(
+            line_chart, sheet_name, start_row, start_col, end_row, value_col,
+        )
+        .set_format(
+            ChartFormat::new().set_line(
+                ChartLine::new()
+                    .set_color(self.config.colors.rolling_mean_line.as_str())
+                    .set_dash_type(ChartLineDashType::Dash),
+            ),
+        );
     }
 
     fn _add_base_chart(
@@ -188,23 +1050,24 @@ impl ConditionWorkbook {
     ) -> Chart {
         let mut col_chart = Chart::new_column();
 
-        col_chart
-            .add_series()
-            .set_name((sheet_name, 0, end_col))
-            .set_categories((sheet_name, start_row, start_col, end_row, start_col))
-            .set_values((sheet_name, start_row, end_col, end_row, end_col))
-            .set_format(
-                ChartFormat::new()
-                    .set_no_border()
-                    .set_solid_fill(ChartSolidFill::new().set_color("#FBE5D6")),
-            )
-            .set_gap(10);
+        self._add_series(
+            &mut col_chart,
+            sheet_name,
+            start_row,
+            start_col,
+            end_row,
+            end_col,
+        )
+        .set_format(ChartFormat::new().set_no_border().set_solid_fill(
+            ChartSolidFill::new().set_color(self.config.colors.holiday_fill.as_str()),
+        ))
+        .set_gap(10);
         col_chart
     }
 
-    fn _set_chart_format(&self, chart: &mut Chart, date_cnt: u32) -> anyhow::Result<()> {
-        chart.set_width(620);
-        chart.set_height(155);
+    fn _set_chart_format(&self, chart: &mut Chart, date_cnt: u32) -> ConditionResult<()> {
+        chart.set_width(self.config.chart_layout.width);
+        chart.set_height(self.config.chart_layout.height);
 
         chart.legend().set_hidden();
         chart
@@ -218,8 +1081,8 @@ impl ConditionWorkbook {
             .set_position_between_ticks(false);
         chart
             .y_axis()
-            .set_min(1)
-            .set_max(5)
+            .set_min(self.config.scale.axis_min())
+            .set_max(self.config.scale.max)
             .set_major_unit(1)
             .set_font(&ChartFont::new().set_size(11))
             .set_major_gridlines(true)
@@ -233,56 +1096,157 @@ impl ConditionWorkbook {
     }
 }
 
-fn extract_yearly_frame_vec(ldf: &LazyFrame) -> Vec<YearlyData> {
-    let df_with_year = ldf
-        .clone()
-        .with_column(col("日付").dt().year().cast(DataType::Int32).alias("year"));
+/// 人の列がある場合は人の名前を並べて返す (空欄の人は「未設定」とする)
+fn person_names(ldf: &LazyFrame) -> PolarsResult<Option<Vec<String>>> {
+    if ldf.clone().collect_schema()?.get(PERSON_COLUMN).is_none() {
+        return Ok(None);
+    }
+    let persons_df = fill_unknown_person(ldf)
+        .select([col(PERSON_COLUMN).unique().sort(SortOptions::default())])
+        .collect()?;
+    let persons = persons_df
+        .column(PERSON_COLUMN)?
+        .str()?
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .collect();
+    Ok(Some(persons))
+}
+
+/// 人の列がある場合は人毎のデータ(人の列を除く)に分ける
+fn person_frames(ldf: &LazyFrame) -> PolarsResult<Option<Vec<(String, LazyFrame)>>> {
+    let Some(persons) = person_names(ldf)? else {
+        return Ok(None);
+    };
+    let ldf = fill_unknown_person(ldf);
+    let frames = persons
+        .into_iter()
+        .map(|person| {
+            let person_ldf = ldf
+                .clone()
+                .filter(col(PERSON_COLUMN).eq(lit(person.as_str())))
+                .drop([col(PERSON_COLUMN)]);
+            (person, person_ldf)
+        })
+        .collect();
+    Ok(Some(frames))
+}
+
+fn fill_unknown_person(ldf: &LazyFrame) -> LazyFrame {
+    ldf.clone().with_column(
+        col(PERSON_COLUMN)
+            .cast(DataType::String)
+            .fill_null(lit(UNKNOWN_PERSON)),
+    )
+}
+
+/// レポートが作成するシートの名前か (年毎のシート、または設定のシート名とその人毎のシート)
+fn is_generated_sheet_name(name: &str, bases: &[String]) -> bool {
+    let prefix = name.split('_').next().unwrap_or_default();
+    if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    bases.iter().any(|base| {
+        name.strip_prefix(base.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('_'))
+    })
+}
+
+/// 人毎のシート名 (エクセルで使えない文字は置き換え、31文字までにする)
+///
+/// 31文字を超える場合は、名前の先頭が同じ人のシートと重ならないよう名前のハッシュを末尾に付ける。
+fn person_sheet_name(base: &str, person: Option<&str>) -> String {
+    let Some(person) = person else {
+        return base.to_string();
+    };
+    let name: Vec<char> = format!("{base}_{person}")
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect();
+    if name.len() <= 31 {
+        return name.into_iter().collect();
+    }
+    let hash: String = Sha256::digest(person.as_bytes())
+        .iter()
+        .take(2)
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let mut truncated: String = name.into_iter().take(26).collect();
+    truncated.push('~');
+    truncated.push_str(&hash);
+    truncated
+}
 
-    let binding = df_with_year
+/// 作成する年毎のシートの数 (作り直す年の指定があればその年のみ数える)
+fn count_yearly_sheets(ldf: &LazyFrame, years: Option<&[i32]>) -> PolarsResult<usize> {
+    let mut keys = vec![col(DATE_COLUMN).dt().year().alias("year")];
+    if ldf.clone().collect_schema()?.get(PERSON_COLUMN).is_some() {
+        keys.push(col(PERSON_COLUMN));
+    }
+    let count_df = ldf
         .clone()
-        .collect()
-        .unwrap()
-        .column("year")
-        .unwrap()
-        .unique()
-        .unwrap();
+        .select(keys)
+        // エクセルのdataシートの日付が空欄の行は年毎のシートを作らない
+        .filter(col("year").is_not_null())
+        .unique(None, UniqueKeepStrategy::Any)
+        .collect()?;
+    Ok(match years {
+        Some(years) => count_df
+            .column("year")?
+            .i32()?
+            .into_iter()
+            .flatten()
+            .filter(|year| years.contains(year))
+            .count(),
+        None => count_df.height(),
+    })
+}
+
+fn extract_yearly_frame_vec(ldf: &LazyFrame) -> PolarsResult<Vec<YearlyData>> {
+    let df_with_year = ldf.clone().with_column(
+        col(DATE_COLUMN)
+            .dt()
+            .year()
+            .cast(DataType::Int32)
+            .alias("year"),
+    );
+
+    let binding = df_with_year.clone().collect()?.column("year")?.unique()?;
 
-    let years = binding.i32().unwrap();
+    let years = binding.i32()?;
 
     let mut yearly_data = vec![];
-    for year in years {
+    // 日付が空欄や読み取れない行はどの年のシートにも含めない
+    for year in years.into_iter().flatten() {
         let yearly_ldf = df_with_year
             .clone()
-            .filter(col("year").eq(lit(year.unwrap_or_default())))
+            .filter(col("year").eq(lit(year)))
             .drop([col("year")]);
 
         yearly_data.push(YearlyData {
-            year: year.unwrap_or_default(),
+            year,
             ldf: yearly_ldf,
         });
     }
-    yearly_data
+    Ok(yearly_data)
 }
 
-fn extract_monthly_frame_vec(ldf: &LazyFrame) -> Vec<MonthlyData> {
+fn extract_monthly_frame_vec(ldf: &LazyFrame) -> PolarsResult<Vec<MonthlyData>> {
     let df_with_month = ldf.clone().with_column(
-        col("日付")
+        col(DATE_COLUMN)
             .dt()
             .month()
             .cast(DataType::Int32)
             .alias("month"),
     );
 
-    let binding = df_with_month
-        .clone()
-        .collect()
-        .unwrap()
-        .column("month")
-        .unwrap()
-        .unique()
-        .unwrap();
+    let binding = df_with_month.clone().collect()?.column("month")?.unique()?;
 
-    let months = binding.i32().unwrap();
+    let months = binding.i32()?;
 
     let mut monthly_data = vec![];
     for month in months {
@@ -295,16 +1259,21 @@ fn extract_monthly_frame_vec(ldf: &LazyFrame) -> Vec<MonthlyData> {
             ldf: monthly_df,
         });
     }
-    monthly_data
+    Ok(monthly_data)
 }
 
-fn prepare_yearly_frame(ldf: &LazyFrame, year: i32) -> LazyFrame {
+fn prepare_yearly_frame(
+    ldf: &LazyFrame,
+    year: i32,
+    holidays: &HolidayCalendar,
+    scale: &ScoreScale,
+) -> PolarsResult<LazyFrame> {
     // 1年分の日付列を準備
     let start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap().into();
     let end = NaiveDate::from_ymd_opt(year, 12, 31).unwrap().into();
     let interval = Duration::parse("1d");
     let dates = date_range(
-        "日付".into(),
+        DATE_COLUMN.into(),
         start,
         end,
         interval,
@@ -316,16 +1285,28 @@ fn prepare_yearly_frame(ldf: &LazyFrame, year: i32) -> LazyFrame {
     let date_col = dates.cast(&DataType::Date).unwrap();
     let mut yearly_ldf = DataFrame::new(vec![date_col.into()]).unwrap().lazy();
 
-    yearly_ldf = yearly_ldf.left_join(ldf.clone(), col("日付"), col("日付"));
+    // 年毎のシートのレイアウトを保つため、追加の列はdataシートのみに残す
+    let condition_ldf =
+        ldf.clone()
+            .select([col(DATE_COLUMN), col(CONDITION_COLUMN), col(COMMENT_COLUMN)]);
+    yearly_ldf = yearly_ldf.left_join(condition_ldf, col(DATE_COLUMN), col(DATE_COLUMN));
+    // 祝日を追加
+    let (holiday_dates, holiday_names): (Vec<NaiveDate>, Vec<String>) =
+        holidays.holidays(year).into_iter().unzip();
+    let holiday_ldf = df!(
+        DATE_COLUMN => holiday_dates,
+        HOLIDAY_COLUMN => holiday_names,
+    )?
+    .lazy();
+    yearly_ldf = yearly_ldf.left_join(holiday_ldf, col(DATE_COLUMN), col(DATE_COLUMN));
     // 曜日を追加
     let weekdays_int_series: Series = [1, 2, 3, 4, 5, 6, 7].iter().collect();
     let weekdays_str_series: Series = ["月", "火", "水", "木", "金", "土", "日"]
         .iter()
         .map(|&s| s)
         .collect();
-    let holidays_int_series: Series = [0, 0, 0, 0, 0, 5, 5].iter().collect();
     yearly_ldf = yearly_ldf.with_columns(vec![
-        col("日付")
+        col(DATE_COLUMN)
             .dt()
             .weekday()
             .replace_strict(
@@ -334,160 +1315,938 @@ fn prepare_yearly_frame(ldf: &LazyFrame, year: i32) -> LazyFrame {
                 None,
                 Some(DataType::String),
             )
-            .alias("曜日"),
-        col("日付")
-            .dt()
-            .weekday()
-            .replace_strict(
-                lit(weekdays_int_series),
-                lit(holidays_int_series),
-                None,
-                Some(DataType::Int32),
-            )
-            .alias("土日判定"),
+            .alias(WEEKDAY_COLUMN),
+        // 土日祝はグラフの背景に色を付けるため体調の最大値とする
+        when(
+            col(DATE_COLUMN)
+                .dt()
+                .weekday()
+                .gt_eq(lit(6))
+                .or(col(HOLIDAY_COLUMN).is_not_null()),
+        )
+        .then(lit(scale.max))
+        .otherwise(lit(scale.min))
+        .alias(DAY_OFF_COLUMN),
     ]);
-    yearly_ldf
+    // 祝日の列は土日判定の後ろに並べる
+    yearly_ldf = yearly_ldf.select([all().exclude([HOLIDAY_COLUMN]), col(HOLIDAY_COLUMN)]);
+
+    // 体調の傾向を表す列を追加 (年の初めは前年のデータを含めずに計算する)
+    let rolling_mean = |days| {
+        col(CONDITION_COLUMN)
+            .cast(DataType::Float64)
+            .rolling_mean(RollingOptionsFixedWindow {
+                window_size: days,
+                min_periods: 1,
+                ..Default::default()
+            })
+    };
+    // 体調が低い日の連続を、低くない日で区切ったまとまり毎に数える
+    let low_day = col(CONDITION_COLUMN)
+        .lt_eq(lit(scale.low_threshold))
+        .fill_null(lit(false));
+    let low_run = low_day
+        .clone()
+        .neq(low_day.clone().shift(lit(1)))
+        .fill_null(lit(true))
+        .cast(DataType::UInt32)
+        .cum_sum(false);
+    Ok(yearly_ldf.with_columns([
+        rolling_mean(7).alias(WEEKLY_MEAN_COLUMN),
+        rolling_mean(30).alias(MONTHLY_MEAN_COLUMN),
+        (col(CONDITION_COLUMN) - col(CONDITION_COLUMN).shift(lit(1))).alias(DAILY_CHANGE_COLUMN),
+        when(low_day.clone())
+            .then(
+                low_day
+                    .cast(DataType::UInt32)
+                    .cum_sum(false)
+                    .over([low_run]),
+            )
+            .otherwise(lit(0u32))
+            .alias(LOW_STREAK_COLUMN),
+    ]))
 }
 
-fn prepare_agg_frame(yearly_ldf: &LazyFrame) -> LazyFrame {
-    // # 年間の体調の集計dfを作成
+fn prepare_agg_frame(yearly_ldf: &LazyFrame, scale: &ScoreScale) -> PolarsResult<LazyFrame> {
+    // # 年間の体調の集計dfを作成 (行は体調の値毎の表示の順)
     let mut agg_ldf = df!(
-        "調子" => ["↑", "↗", "→", "↘", "↓", "⇓"],
-        "体調" => [5, 4, 3, 2, 1, 0]
+        "調子" => scale.levels.iter().map(|level| level.label.as_str()).collect::<Vec<_>>(),
+        CONDITION_COLUMN => scale.levels.iter().map(|level| level.value).collect::<Vec<_>>()
     )
     .unwrap()
     .lazy();
 
     let yearly_agg_ldf = yearly_ldf
         .clone()
-        .group_by([col("体調")])
-        .agg([col("日付").count().alias("年間")])
-        .select([col("体調"), col("年間")]);
+        .group_by([col(CONDITION_COLUMN)])
+        .agg([col(DATE_COLUMN).count().alias("年間")])
+        .select([col(CONDITION_COLUMN), col("年間")]);
 
-    agg_ldf = agg_ldf.left_join(yearly_agg_ldf, col("体調"), col("体調"));
+    agg_ldf = agg_ldf.left_join(yearly_agg_ldf, col(CONDITION_COLUMN), col(CONDITION_COLUMN));
 
     // 月毎の集計を追加
-    for monthly_data in extract_monthly_frame_vec(yearly_ldf) {
+    for monthly_data in extract_monthly_frame_vec(yearly_ldf)? {
         let jp_month_str = format!("{}月", monthly_data.month);
         // 集計表に月毎の体調の集計を追加
         let temp_agg_ldf = monthly_data
             .ldf
-            .group_by([col("体調")])
-            .agg([col("体調").count().alias(&jp_month_str)])
-            .select([col("体調"), col(&jp_month_str)]);
-        agg_ldf = agg_ldf.left_join(temp_agg_ldf, col("体調"), col("体調"));
+            .group_by([col(CONDITION_COLUMN)])
+            .agg([col(CONDITION_COLUMN).count().alias(&jp_month_str)])
+            .select([col(CONDITION_COLUMN), col(&jp_month_str)]);
+        agg_ldf = agg_ldf.left_join(temp_agg_ldf, col(CONDITION_COLUMN), col(CONDITION_COLUMN));
     }
-    agg_ldf.fill_null(lit(0))
+    Ok(agg_ldf.fill_null(lit(0)))
 }
 
-#[tauri::command]
-fn write_excel(csv_path: &str, excel_path: &str, save_path: &str) -> Result<(), String> {
-    let additional_condition_df = match read_csv(Some(csv_path.into())) {
-        Ok(df) => df,
-        Err(e) => return Err(e.to_string()),
-    };
-    let ori_condition_df = match read_excel(excel_path) {
-        Ok(df) => df,
-        Err(e) => return Err(e.to_string()),
-    };
-    let merged_ldf = merge_condition_data(&additional_condition_df, &ori_condition_df);
-    let mut workbook = ConditionWorkbook::new();
-    match workbook.write(&merged_ldf, save_path) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string()),
+/// 体調毎の日数の集計表の下に、統計の行を加えた表を作る
+fn prepare_agg_table(yearly_ldf: &LazyFrame, scale: &ScoreScale) -> PolarsResult<LazyFrame> {
+    concat_lf_diagonal(
+        [
+            prepare_agg_frame(yearly_ldf, scale)?,
+            prepare_agg_summary_frame(yearly_ldf)?,
+        ],
+        UnionArgs {
+            to_supertypes: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// 統計の行の名前と、体調の列から値を計算する式
+type SummaryStat = (&'static str, fn(Expr) -> Expr);
+
+/// 年間と月毎の体調の平均・中央値・標準偏差・記録日数・未記録日数の行を作る
+///
+/// 未記録日数はその年の最後の記録までを数え、まだ来ていない日は含めない。
+fn prepare_agg_summary_frame(yearly_ldf: &LazyFrame) -> PolarsResult<LazyFrame> {
+    let recorded_ldf = yearly_ldf.clone().with_column(
+        col(DATE_COLUMN)
+            .filter(col(CONDITION_COLUMN).is_not_null())
+            .max()
+            .alias("最終記録日"),
+    );
+    let in_period = col(DATE_COLUMN).lt_eq(col("最終記録日"));
+    let stats: [SummaryStat; 5] = [
+        ("平均", |e| e.cast(DataType::Float64).mean().round(2)),
+        ("中央値", |e| e.cast(DataType::Float64).median()),
+        ("標準偏差", |e| {
+            e.cast(DataType::Float64).std(1).round(2)
+        }),
+        ("記録日数", |e| {
+            e.is_not_null().cast(DataType::Float64).sum()
+        }),
+        ("未記録日数", |e| {
+            e.is_null().cast(DataType::Float64).sum()
+        }),
+    ];
+
+    let mut stat_ldfs = vec![];
+    for (label, stat) in stats {
+        let mut exprs = vec![
+            lit(label).alias("調子"),
+            stat(col(CONDITION_COLUMN).filter(in_period.clone())).alias("年間"),
+        ];
+        for month in 1..=12 {
+            let in_month = col(DATE_COLUMN).dt().month().eq(lit(month));
+            exprs.push(
+                stat(col(CONDITION_COLUMN).filter(in_period.clone().and(in_month)))
+                    .alias(format!("{month}月")),
+            );
+        }
+        stat_ldfs.push(recorded_ldf.clone().select(exprs));
     }
+    concat(stat_ldfs, UnionArgs::default())
 }
 
-fn read_csv(path: Option<PathBuf>) -> PolarsResult<DataFrame> {
-    let schema = Schema::from_iter(vec![
-        Field::new("日付".into(), DataType::Date),
-        Field::new("体調".into(), DataType::Int32),
-        Field::new("コメント".into(), DataType::String),
-    ]);
-    match CsvReadOptions::default()
-        .with_has_header(false)
-        .with_skip_rows(2)
-        .with_schema(Some(Arc::new(schema)))
-        .try_into_reader_with_file_path(path)
-    {
-        Ok(csv_reader) => csv_reader
-            .finish()?
-            .drop_nulls(Some(&vec!["日付".to_string()]))?
-            .sort(
-                ["日付"],
-                SortMultipleOptions::new()
-                    .with_order_descending(false)
-                    .with_nulls_last(true),
-            ),
-        Err(e) => return Err(e.into()),
+/// 曜日毎と平日・土日祝毎の体調の平均・中央値・分布の表を作る
+///
+/// `prepare_yearly_frame`で曜日と土日判定を追加したデータを受け取る。
+fn prepare_weekday_frame(yearly_ldf: &LazyFrame, scale: &ScoreScale) -> PolarsResult<LazyFrame> {
+    let order_ldf = df!(
+        "区分" => ["月", "火", "水", "木", "金", "土", "日", "平日", "土日祝"]
+    )?
+    .lazy();
+
+    let mut agg_exprs = vec![
+        col(CONDITION_COLUMN).mean().alias("平均"),
+        col(CONDITION_COLUMN).median().alias("中央値"),
+        col(CONDITION_COLUMN)
+            .count()
+            .cast(DataType::UInt32)
+            .alias("記録日数"),
+    ];
+    for level in &scale.levels {
+        agg_exprs.push(
+            col(CONDITION_COLUMN)
+                .eq(lit(level.value))
+                .cast(DataType::UInt32)
+                .sum()
+                .alias(level.column_name()),
+        );
     }
+    let weekday_ldf = yearly_ldf
+        .clone()
+        .with_column(col(WEEKDAY_COLUMN).alias("区分"))
+        .group_by([col("区分")])
+        .agg(agg_exprs.clone());
+    let day_type_ldf = yearly_ldf
+        .clone()
+        .with_column(
+            when(col(DAY_OFF_COLUMN).eq(lit(scale.max)))
+                .then(lit("土日祝"))
+                .otherwise(lit("平日"))
+                .alias("区分"),
+        )
+        .group_by([col("区分")])
+        .agg(agg_exprs);
+
+    let agg_ldf = concat([weekday_ldf, day_type_ldf], UnionArgs::default())?;
+    Ok(order_ldf.left_join(agg_ldf, col("区分"), col("区分")))
 }
 
-fn read_excel(path: &str) -> anyhow::Result<DataFrame> {
-    let mut excel: Xlsx<_> = open_workbook(path).unwrap();
+/// 最後の記録までの未記録の日を、連続した期間毎にまとめる
+///
+/// 未記録日数の集計と同じく、その年の最後の記録より後の日は含めない。
+fn prepare_missing_ranges_frame(yearly_ldf: &LazyFrame) -> LazyFrame {
+    let missing = col(CONDITION_COLUMN).is_null();
+    let missing_run = missing
+        .clone()
+        .neq(missing.clone().shift(lit(1)))
+        .fill_null(lit(true))
+        .cast(DataType::UInt32)
+        .cum_sum(false);
+    recorded_period(yearly_ldf)
+        .with_column(missing_run.alias("期間"))
+        .filter(missing)
+        .group_by([col("期間")])
+        .agg([
+            col(DATE_COLUMN).min().alias("開始日"),
+            col(DATE_COLUMN).max().alias("終了日"),
+            col(DATE_COLUMN)
+                .count()
+                .cast(DataType::UInt32)
+                .alias("日数"),
+        ])
+        .sort(["開始日"], Default::default())
+        .select([col("開始日"), col("終了日"), col("日数")])
+}
 
-    let range = match excel.worksheet_range("data") {
-        Ok(range) => range,
-        Err(e) => return Err(anyhow::anyhow!(e)),
-    };
+/// 月毎と年間の記録率の表を作る (最後の記録までの日数に対する記録日数の割合)
+fn prepare_completeness_frame(yearly_ldf: &LazyFrame) -> PolarsResult<LazyFrame> {
+    let period_ldf = recorded_period(yearly_ldf);
+    let agg_exprs = [
+        col(DATE_COLUMN)
+            .count()
+            .cast(DataType::UInt32)
+            .alias("日数"),
+        col(CONDITION_COLUMN)
+            .count()
+            .cast(DataType::UInt32)
+            .alias("記録日数"),
+        col(CONDITION_COLUMN)
+            .null_count()
+            .cast(DataType::UInt32)
+            .alias("未記録日数"),
+    ];
+    let monthly_ldf = period_ldf
+        .clone()
+        .group_by([col(DATE_COLUMN).dt().month().alias("月番号")])
+        .agg(agg_exprs.clone())
+        .sort(["月番号"], Default::default())
+        .select([
+            concat_str([col("月番号").cast(DataType::String), lit("月")], "", false).alias("月"),
+            col("日数"),
+            col("記録日数"),
+            col("未記録日数"),
+        ]);
+    let mut annual_exprs = vec![lit("年間").alias("月")];
+    annual_exprs.extend(agg_exprs);
+    let annual_ldf = period_ldf.select(annual_exprs);
+
+    let completeness_ldf = concat([monthly_ldf, annual_ldf], UnionArgs::default())?;
+    // 記録が1日も無い年は日数が0になるので記録率は空欄にする
+    Ok(completeness_ldf.with_column(
+        when(col("日数").gt(lit(0)))
+            .then(
+                (col("記録日数").cast(DataType::Float64) / col("日数").cast(DataType::Float64)
+                    * lit(100.0))
+                .round(1),
+            )
+            .otherwise(lit(NULL).cast(DataType::Float64))
+            .alias("記録率"),
+    ))
+}
 
-    let mut dates: Vec<Option<NaiveDate>> = vec![];
-    let mut conditions: Vec<Option<i32>> = vec![];
-    let mut comments: Vec<Option<String>> = vec![];
-    for row in range.rows().skip(1) {
-        let date = match calamine::DataType::as_date(&row[0]) {
-            Some(value) => Some(value),
-            None => None,
-        };
-        let condition = match calamine::DataType::as_i64(&row[1]) {
-            Some(value) => Some(value as i32),
-            None => None,
-        };
-        let comment = match calamine::DataType::as_string(&row[2]) {
-            Some(value) => Some(value),
-            None => None,
-        };
+/// 年毎のデータを、その年の最後の記録の日までに絞る
+fn recorded_period(yearly_ldf: &LazyFrame) -> LazyFrame {
+    yearly_ldf
+        .clone()
+        .with_column(
+            col(DATE_COLUMN)
+                .filter(col(CONDITION_COLUMN).is_not_null())
+                .max()
+                .alias("最終記録日"),
+        )
+        .filter(col(DATE_COLUMN).lt_eq(col("最終記録日")))
+}
 
-        dates.push(date);
-        conditions.push(condition);
-        comments.push(comment);
-    }
-    df!(
-        "日付" => dates,
-        "体調" => conditions,
-        "コメント" => comments,
-    )
-    .map_err(|e| anyhow::anyhow!(e))
+/// 作成中のレポートの中断用トークン (キーはフロントエンドが指定するジョブID)
+#[derive(Default)]
+struct ReportJobs(Mutex<HashMap<String, CancelToken>>);
+
+/// 取り込んだデータの保存先 (レポートの作成中も別スレッドから使うため共有する)
+struct DataStore(Arc<Mutex<ConditionStore>>);
+
+/// フロントエンドに送る進み具合のイベント
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent {
+    job_id: String,
+    #[serde(flatten)]
+    progress: ReportProgress,
 }
 
-fn merge_condition_data(csv_df: &DataFrame, condition_df: &DataFrame) -> LazyFrame {
-    condition_df
-        .vstack(csv_df)
+/// 進み具合を通知するイベント名
+const PROGRESS_EVENT: &str = "report-progress";
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn write_excel(
+    app: AppHandle,
+    jobs: State<'_, ReportJobs>,
+    store: State<'_, DataStore>,
+    job_id: String,
+    csv_paths: Vec<String>,
+    excel_path: Option<String>,
+    save_path: String,
+    custom_holidays: Option<Vec<NaiveDate>>,
+    options: Option<LoadOptions>,
+    sheets: Option<SheetOptions>,
+    update: Option<bool>,
+) -> ConditionResult<ReportSummary> {
+    let config = ReportConfig::load(&report_config_path(&app)?)?;
+    let options = with_report_config(options.unwrap_or_default(), &config);
+    let cancel_token = CancelToken::new();
+    jobs.0
+        .lock()
         .unwrap()
-        .lazy()
-        .unique(Some(vec!["日付".to_string()]), UniqueKeepStrategy::Last)
-        .sort(
-            ["日付"],
-            SortMultipleOptions::new()
-                .with_order_descending(false)
-                .with_nulls_last(true),
+        .insert(job_id.clone(), cancel_token.clone());
+    let progress = Progress::new({
+        let job_id = job_id.clone();
+        move |progress| {
+            let event = ProgressEvent {
+                job_id: job_id.clone(),
+                progress: progress.clone(),
+            };
+            // 通知できなくてもレポートの作成は続ける
+            let _ = app.emit(PROGRESS_EVENT, event);
+        }
+    })
+    .with_cancel_token(cancel_token);
+    let store = store.0.clone();
+
+    // 集計とグラフの作成は時間がかかるため、非同期ランタイムを止めないよう別スレッドで行う
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        create_report(
+            Some(&store),
+            &csv_paths,
+            excel_path.as_deref(),
+            &save_path,
+            custom_holidays.unwrap_or_default(),
+            &options,
+            &sheets.unwrap_or_default(),
+            &config,
+            update.unwrap_or_default(),
+            &progress,
         )
+    })
+    .await;
+    jobs.0.lock().unwrap().remove(&job_id);
+    result.map_err(|e| ConditionError::Internal(e.to_string()))?
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![write_excel])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+/// 保存せずに保存先のデータとの結合結果・年毎の集計・概要を返す
+#[tauri::command]
+async fn preview_data(
+    app: AppHandle,
+    store: State<'_, DataStore>,
+    csv_paths: Vec<String>,
+    excel_path: Option<String>,
+    options: Option<LoadOptions>,
+) -> ConditionResult<DataPreview> {
+    let config = ReportConfig::load(&report_config_path(&app)?)?;
+    let options = with_report_config(options.unwrap_or_default(), &config);
+    let store = store.0.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = store.lock().unwrap();
+        let data = if csv_paths.is_empty() && excel_path.is_none() {
+            stored_condition_data(&store)?
+        } else {
+            let data = load_condition_data(&csv_paths, excel_path.as_deref(), &options)?;
+            let data = with_stored_persons(&store, data, &options)?;
+            store.merge(data, options.merge_strategy)?
+        };
+        preview_condition_data(data, &config.scale)
+    })
+    .await
+    .map_err(|e| ConditionError::Internal(e.to_string()))?
 }
 
-#[cfg(test)]
+/// 作成中のレポートの中断を指示する (該当するジョブが無ければfalse)
+#[tauri::command]
+fn cancel_report(jobs: State<'_, ReportJobs>, job_id: String) -> bool {
+    match jobs.0.lock().unwrap().get(&job_id) {
+        Some(cancel_token) => {
+            cancel_token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// 保存されている記録を期間で絞り込んで返す (期間の指定が無ければ全て)
+#[tauri::command]
+async fn list_entries(
+    store: State<'_, DataStore>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> ConditionResult<Vec<ConditionEntry>> {
+    with_store(&store, move |store| store.entries(from, to)).await
+}
+
+/// 記録を手で追加する (体調は設定した範囲で検証する)
+#[tauri::command]
+async fn add_entry(
+    app: AppHandle,
+    store: State<'_, DataStore>,
+    entry: EntryInput,
+) -> ConditionResult<ConditionEntry> {
+    let config = ReportConfig::load(&report_config_path(&app)?)?;
+    with_store(&store, move |store| store.add_entry(entry, &config.scale)).await
+}
+
+/// 保存されている記録の体調とコメントを修正する
+#[tauri::command]
+async fn update_entry(
+    app: AppHandle,
+    store: State<'_, DataStore>,
+    entry: EntryInput,
+) -> ConditionResult<ConditionEntry> {
+    let config = ReportConfig::load(&report_config_path(&app)?)?;
+    with_store(&store, move |store| {
+        store.update_entry(entry, &config.scale)
+    })
+    .await
+}
+
+/// 保存されている記録を削除する
+#[tauri::command]
+async fn delete_entry(
+    store: State<'_, DataStore>,
+    date: NaiveDate,
+    person: Option<String>,
+) -> ConditionResult<()> {
+    with_store(&store, move |store| store.delete_entry(date, person)).await
+}
+
+/// これまでの取り込みを新しい順に返す
+#[tauri::command]
+async fn list_imports(store: State<'_, DataStore>) -> ConditionResult<Vec<ImportBatch>> {
+    with_store(&store, |store| store.imports()).await
+}
+
+/// 取り込みを取り消し、保存されているデータをその取り込みの前の値に戻す
+#[tauri::command]
+async fn rollback_import(store: State<'_, DataStore>, id: i64) -> ConditionResult<RollbackSummary> {
+    with_store(&store, move |store| store.rollback(id)).await
+}
+
+/// 保存先を使う処理を別スレッドで行う (レポートの作成中はロックを待つため非同期ランタイムを止めない)
+async fn with_store<T: Send + Unpin + 'static>(
+    store: &DataStore,
+    f: impl FnOnce(&mut ConditionStore) -> ConditionResult<T> + Send + 'static,
+) -> ConditionResult<T> {
+    let store = store.0.clone();
+    tauri::async_runtime::spawn_blocking(move || f(&mut store.lock().unwrap()))
+        .await
+        .map_err(|e| ConditionError::Internal(e.to_string()))?
+}
+
+/// アプリの設定ディレクトリにあるレポート設定のパス
+fn report_config_path(app: &AppHandle) -> ConditionResult<PathBuf> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| ConditionError::Internal(e.to_string()))?;
+    Ok(dir.join(REPORT_CONFIG_FILE))
+}
+
+/// 前回のエクセルファイルは設定したdataシートの名前で読み込み、設定した体調の範囲で検証する
+fn with_report_config(mut options: LoadOptions, config: &ReportConfig) -> LoadOptions {
+    options
+        .data_sheet
+        .get_or_insert_with(|| config.sheet_names.data.clone());
+    options.scale.get_or_insert_with(|| config.scale.clone());
+    options
+}
+
+/// 保存されているレポート設定を返す (保存されていなければ既定の設定)
+#[tauri::command]
+fn get_report_config(app: AppHandle) -> ConditionResult<ReportConfig> {
+    ReportConfig::load(&report_config_path(&app)?)
+}
+
+/// レポート設定を検証して保存する
+#[tauri::command]
+fn save_report_config(app: AppHandle, config: ReportConfig) -> ConditionResult<()> {
+    config.save(&report_config_path(&app)?)
+}
+
+/// レポートに追加で書き出すシート
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SheetOptions {
+    /// 結合時に値が異なっていた項目 (conflictsシート)
+    pub conflicts: bool,
+    /// 読み込み時の検証結果 (検証結果シート)
+    pub diagnostics: bool,
+}
+
+/// レポート作成後にフロントエンドへ返す結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSummary {
+    pub conflicts: Vec<MergeConflict>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// 前回のレポートを更新した場合の結果
+    pub update: Option<UpdateSummary>,
+    /// 保存先に取り込んだ場合の記録
+    pub import: Option<ImportBatch>,
+}
+
+/// 前回のレポートを更新した結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSummary {
+    /// シートを作り直した年
+    pub years: Vec<i32>,
+    /// 前回のレポートから移したシート
+    pub carried_sheets: Vec<String>,
+}
+
+/// CSVとエクセルを結合し、年毎の集計シートとグラフを含むレポートを保存する
+///
+/// 保存先があれば読み込んだデータをそこへ取り込み、保存されている全てのデータからレポートを作成する。
+/// 保存先のロックは取り込みの間だけ持ち、エクセルの書き出し中は他のコマンドから保存先を使える。
+/// `update`を指定すると前回のエクセルファイルの値を優先して新しい日付だけを加え、
+/// 前回のファイルのシートを残したまま、データが増えた年のシートだけを作り直す。
+#[allow(clippy::too_many_arguments)]
+fn create_report(
+    store: Option<&Mutex<ConditionStore>>,
+    csv_paths: &[String],
+    excel_path: Option<&str>,
+    save_path: &str,
+    custom_holidays: Vec<NaiveDate>,
+    options: &LoadOptions,
+    sheets: &SheetOptions,
+    config: &ReportConfig,
+    update: bool,
+    progress: &Progress,
+) -> ConditionResult<ReportSummary> {
+    let previous = excel_path.filter(|_| update);
+    let mut options = options.clone();
+    if previous.is_some() {
+        options.merge_strategy = MergeStrategy::PreferExcel;
+    }
+    let data = match store {
+        Some(store) => import_condition_data_with_progress(
+            &mut store.lock().unwrap(),
+            csv_paths,
+            excel_path,
+            &options,
+            progress,
+        )?,
+        None => load_condition_data_with_progress(csv_paths, excel_path, &options, progress)?,
+    };
+    let mut workbook = ConditionWorkbook::new();
+    workbook
+        .set_holidays(HolidayCalendar::new().with_custom_holidays(custom_holidays))
+        .set_progress(progress.clone())
+        .set_config(config.clone());
+    if sheets.conflicts {
+        workbook.set_conflicts(data.conflicts.clone());
+    }
+    if sheets.diagnostics {
+        workbook.set_diagnostics(data.diagnostics.clone());
+    }
+    let update = match previous {
+        Some(previous) => Some(workbook.update(&data.ldf, previous, save_path)?),
+        None => {
+            workbook.write(&data.ldf, save_path)?;
+            None
+        }
+    };
+    Ok(ReportSummary {
+        conflicts: data.conflicts,
+        diagnostics: data.diagnostics,
+        update,
+        import: data.import,
+    })
+}
+
+/// CSVとエクセルの読み込み・結合の設定
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LoadOptions {
+    pub csv: CsvImportOptions,
+    pub merge_strategy: MergeStrategy,
+    /// 前回のエクセルファイルで読み込むシート (指定が無ければdataシート)
+    pub data_sheet: Option<String>,
+    /// 検証に使う体調の範囲 (指定が無ければ0〜5)
+    pub scale: Option<ScoreScale>,
+}
+
+/// 読み込んで結合したデータと、その過程で見つかった問題
+pub struct ConditionData {
+    pub ldf: LazyFrame,
+    pub conflicts: Vec<MergeConflict>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// 保存先に取り込んだ場合の記録
+    pub import: Option<ImportBatch>,
+}
+
+/// CSVと前回のエクセルファイル(あれば)を読み込み、結合したデータを返す
+///
+/// CSVは指定した順に結合するため、既定の方針では後に指定したCSVのものが優先される。
+/// 各ファイルは結合前に検証し、問題は`diagnostics`に残す(読み込みは止めない)。
+pub fn load_condition_data(
+    csv_paths: &[impl AsRef<Path>],
+    excel_path: Option<impl AsRef<Path>>,
+    options: &LoadOptions,
+) -> ConditionResult<ConditionData> {
+    load_condition_data_with_progress(csv_paths, excel_path, options, &Progress::default())
+}
+
+/// ファイル毎の読み込みと結合の進み具合を通知しながらデータを読み込む
+pub fn load_condition_data_with_progress(
+    csv_paths: &[impl AsRef<Path>],
+    excel_path: Option<impl AsRef<Path>>,
+    options: &LoadOptions,
+    progress: &Progress,
+) -> ConditionResult<ConditionData> {
+    if csv_paths.is_empty() && excel_path.is_none() {
+        return Err(ConditionError::NoInput);
+    }
+    let today = chrono::Local::now().date_naive();
+    let scale = options.scale.clone().unwrap_or_default();
+    let mut diagnostics = vec![];
+    let file_total = csv_paths.len() + usize::from(excel_path.is_some());
+    let mut condition_df = match excel_path {
+        Some(path) => {
+            let path = path.as_ref();
+            progress.report(ReportProgress::Parsing {
+                path: path.into(),
+                index: 1,
+                total: file_total,
+            })?;
+            let sheet_name = options.data_sheet.as_deref().unwrap_or(DATA_SHEET);
+            let excel_df = read_excel_with_rows(path, sheet_name, &mut diagnostics)?;
+            take_validated(excel_df, path, today, &scale, &mut diagnostics)?
+        }
+        None => empty_condition_frame()?,
+    };
+    let mut conflicts = vec![];
+    for (i, csv_path) in csv_paths.iter().enumerate() {
+        progress.report(ReportProgress::Parsing {
+            path: csv_path.as_ref().into(),
+            index: file_total - csv_paths.len() + i + 1,
+            total: file_total,
+        })?;
+        let csv_df = csv_import::read_rhythm_care_csv_with_rows(
+            csv_path.as_ref(),
+            &options.csv,
+            &mut diagnostics,
+        )?;
+        let csv_df = take_validated(csv_df, csv_path.as_ref(), today, &scale, &mut diagnostics)?;
+        // 人の列の無いCSVを複数人のデータに結合する場合は、読み込む人として選んだ人のデータとする
+        let additional_condition_df = assign_person(
+            csv_df,
+            &named_persons(&condition_df)?,
+            options.csv.person.as_deref(),
+        )?;
+        progress.report(ReportProgress::Merging {
+            index: i + 1,
+            total: csv_paths.len(),
+        })?;
+        let merged = merge_condition_data_with(
+            &additional_condition_df,
+            &condition_df,
+            options.merge_strategy,
+        )?;
+        conflicts.extend(merged.conflicts);
+        condition_df = merged.ldf.collect()?;
+    }
+    Ok(ConditionData {
+        ldf: condition_df.lazy(),
+        conflicts,
+        diagnostics,
+        import: None,
+    })
+}
+
+/// CSVと前回のエクセルファイル(あれば)を読み込んで保存先に取り込み、保存されている全てのデータを返す
+///
+/// ファイルの指定が無ければ、保存されているデータだけを返す。
+pub fn import_condition_data(
+    store: &mut ConditionStore,
+    csv_paths: &[impl AsRef<Path>],
+    excel_path: Option<impl AsRef<Path>>,
+    options: &LoadOptions,
+) -> ConditionResult<ConditionData> {
+    import_condition_data_with_progress(store, csv_paths, excel_path, options, &Progress::default())
+}
+
+/// 読み込みと結合の進み具合を通知しながら保存先にデータを取り込む
+pub fn import_condition_data_with_progress(
+    store: &mut ConditionStore,
+    csv_paths: &[impl AsRef<Path>],
+    excel_path: Option<impl AsRef<Path>>,
+    options: &LoadOptions,
+    progress: &Progress,
+) -> ConditionResult<ConditionData> {
+    if csv_paths.is_empty() && excel_path.is_none() {
+        return stored_condition_data(store);
+    }
+    let sources = excel_path
+        .as_ref()
+        .map(AsRef::as_ref)
+        .into_iter()
+        .chain(csv_paths.iter().map(AsRef::as_ref))
+        .map(ImportSource::read)
+        .collect::<ConditionResult<Vec<_>>>()?;
+    let data = load_condition_data_with_progress(csv_paths, excel_path, options, progress)?;
+    let data = with_stored_persons(store, data, options)?;
+    store.import(data, options.merge_strategy, sources)
+}
+
+/// 保存先が複数人のデータなら、人の列の無い読み込んだデータをCSVで読み込む人として選んだ人のデータにする
+fn with_stored_persons(
+    store: &ConditionStore,
+    mut data: ConditionData,
+    options: &LoadOptions,
+) -> ConditionResult<ConditionData> {
+    let df = data.ldf.collect()?;
+    let df = assign_person(df, &store.persons()?, options.csv.person.as_deref())?;
+    data.ldf = df.lazy();
+    Ok(data)
+}
+
+/// 保存されているデータ (何も保存されていなければ読み込むファイルの指定が無いエラー)
+fn stored_condition_data(store: &ConditionStore) -> ConditionResult<ConditionData> {
+    let df = store.load()?;
+    if df.height() == 0 {
+        return Err(ConditionError::NoInput);
+    }
+    Ok(ConditionData {
+        ldf: df.lazy(),
+        conflicts: vec![],
+        diagnostics: vec![],
+        import: None,
+    })
+}
+
+/// 前回のエクセルファイルが無い場合の結合元となる空のデータ
+fn empty_condition_frame() -> PolarsResult<DataFrame> {
+    ConditionRecord::to_frame(&[], false)
+}
+
+/// Rhythm CareのCSVを書式を自動判定して読み込む
+pub fn read_csv(path: Option<PathBuf>) -> ConditionResult<DataFrame> {
+    read_csv_with_options(path, &CsvImportOptions::default())
+}
+
+pub fn read_csv_with_options(
+    path: Option<PathBuf>,
+    options: &CsvImportOptions,
+) -> ConditionResult<DataFrame> {
+    csv_import::read_rhythm_care_csv(&path.unwrap_or_default(), options)
+}
+
+pub fn read_excel(path: impl AsRef<Path>) -> ConditionResult<DataFrame> {
+    Ok(read_excel_with_rows(path, DATA_SHEET, &mut vec![])?.drop(ROW_COLUMN)?)
+}
+
+/// dataシートでの行番号の列を残して読み込み、読み取れないセルを検証結果に加える
+fn read_excel_with_rows(
+    path: impl AsRef<Path>,
+    sheet_name: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> ConditionResult<DataFrame> {
+    let file_path = path.as_ref();
+    let mut excel: Xlsx<_> =
+        open_workbook(file_path).map_err(|e| ConditionError::excel_open(file_path, e))?;
+
+    if !excel.sheet_names().iter().any(|name| name == sheet_name) {
+        return Err(ConditionError::SheetNotFound {
+            path: file_path.into(),
+            sheet: sheet_name.to_string(),
+        });
+    }
+    let range = excel
+        .worksheet_range(sheet_name)
+        .map_err(|e| ConditionError::excel_open(file_path, e))?;
+
+    // 列の位置ではなく見出しで列を特定する (英語の見出しは日本語の列名に揃える)
+    let header: Vec<String> = match range.rows().next() {
+        Some(row) => row
+            .iter()
+            .map(|cell| schema::column_name(cell.to_string().trim()).into())
+            .collect(),
+        None => vec![],
+    };
+    let find_column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| ConditionError::ColumnNotFound {
+                path: file_path.into(),
+                sheet: sheet_name.to_string(),
+                column: name.to_string(),
+            })
+    };
+    let date_idx = find_column(DATE_COLUMN)?;
+    let condition_idx = find_column(CONDITION_COLUMN)?;
+    let comment_idx = find_column(COMMENT_COLUMN)?;
+
+    let mut row_numbers: Vec<u32> = vec![];
+    let mut records: Vec<ConditionRecord> = vec![];
+    for (i, row) in range.rows().skip(1).enumerate() {
+        let row_number = i + 2;
+        let date = row.get(date_idx).and_then(calamine::DataType::as_date);
+        let condition = row
+            .get(condition_idx)
+            .and_then(calamine::DataType::as_i64)
+            .map(|value| value as i32);
+        let comment = row.get(comment_idx).and_then(calamine::DataType::as_string);
+
+        // 値はあるが日付や数値として読み取れないセルは空欄として扱い、検証結果に残す
+        for (idx, column, parsed) in [
+            (date_idx, DATE_COLUMN, date.is_some()),
+            (condition_idx, CONDITION_COLUMN, condition.is_some()),
+        ] {
+            match row.get(idx) {
+                Some(cell) if !parsed && !calamine::DataType::is_empty(cell) => {
+                    diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::Unparseable,
+                        path: file_path.into(),
+                        row: Some(row_number),
+                        date,
+                        column: column.into(),
+                        value: Some(cell.to_string()),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        row_numbers.push(row_number as u32);
+        records.push(ConditionRecord {
+            date,
+            person: None,
+            condition,
+            comment,
+        });
+    }
+    let mut df = ConditionRecord::to_frame(&records, false)?;
+
+    // 睡眠時間や服薬など利用者が追加した列も読み込む
+    // (見出しが重なる列は読み飛ばさず、「睡眠時間_2」のように番号を付ける)
+    for (idx, name) in header.iter().enumerate() {
+        if name.is_empty() || [date_idx, condition_idx, comment_idx].contains(&idx) {
+            continue;
+        }
+        let mut unique_name = name.clone();
+        for n in 2.. {
+            if df.get_column_index(&unique_name).is_none() {
+                break;
+            }
+            unique_name = format!("{name}_{n}");
+        }
+        let cells: Vec<Option<&calamine::Data>> =
+            range.rows().skip(1).map(|row| row.get(idx)).collect();
+        df.with_column(excel_column_to_series(&unique_name, &cells))?;
+    }
+    df.with_column(Series::new(ROW_COLUMN.into(), row_numbers))?;
+    Ok(df)
+}
+
+/// エクセルの列を値の型を推定してSeriesに変換する
+fn excel_column_to_series(name: &str, cells: &[Option<&calamine::Data>]) -> Series {
+    use calamine::DataType as _;
+
+    let non_empty: Vec<&calamine::Data> = cells
+        .iter()
+        .flatten()
+        .filter(|c| !c.is_empty())
+        .copied()
+        .collect();
+    let all_cells =
+        |f: fn(&calamine::Data) -> bool| !non_empty.is_empty() && non_empty.iter().all(|c| f(c));
+
+    if all_cells(|c| c.is_int() || c.is_float()) {
+        let values: Vec<Option<f64>> = cells.iter().map(|c| c.and_then(|c| c.as_f64())).collect();
+        Series::new(name.into(), values)
+    } else if all_cells(|c| c.is_bool()) {
+        let values: Vec<Option<bool>> =
+            cells.iter().map(|c| c.and_then(|c| c.get_bool())).collect();
+        Series::new(name.into(), values)
+    } else if all_cells(|c| c.is_datetime() || c.is_datetime_iso()) {
+        let values: Vec<Option<NaiveDate>> =
+            cells.iter().map(|c| c.and_then(|c| c.as_date())).collect();
+        Series::new(name.into(), values)
+    } else {
+        let values: Vec<Option<String>> = cells
+            .iter()
+            .map(|c| c.filter(|c| !c.is_empty()).map(|c| c.to_string()))
+            .collect();
+        Series::new(name.into(), values)
+    }
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(ReportJobs::default())
+        .setup(|app| {
+            let path = app.path().app_data_dir()?.join(STORE_FILE);
+            let store = ConditionStore::open(path)?;
+            app.manage(DataStore(Arc::new(Mutex::new(store))));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            write_excel,
+            preview_data,
+            cancel_report,
+            list_entries,
+            add_entry,
+            update_entry,
+            delete_entry,
+            list_imports,
+            rollback_import,
+            get_report_config,
+            save_report_config
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
+    use calamine::Data;
+    use chrono::{Datelike, NaiveDate};
     use rust_xlsxwriter::{ExcelDateTime, Format};
     // use rust_xlsxwriter::*;
     use std::fs::File;
@@ -583,6 +2342,780 @@ mod tests {
         assert!(df.equals_missing(&expected_df));
     }
 
+    #[test]
+    fn test_read_excel_missing_file() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("missing.xlsx");
+
+        let err = read_excel(file_path.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FileNotFound);
+        assert_eq!(err.path(), Some(file_path.as_path()));
+    }
+
+    #[test]
+    fn test_read_excel_without_data_sheet() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("Sheet1").unwrap();
+        workbook.save(&file_path).unwrap();
+
+        let err = read_excel(file_path.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SheetNotFound);
+
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "sheetNotFound");
+        assert_eq!(json["path"], file_path.to_str().unwrap());
+    }
+
+    /// test_read_csvと同じ内容のCSV
+    const RHYTHM_CARE_CSV: &str = "日付,\"愛さん体調\",\"愛さん体調\"\n\
+                                   ,\"\",コメント\n\
+                                   2025/01/27,,\n\
+                                   ,2,Should be dropped\n\
+                                   2025/01/28,3,テストコメント①髙\n";
+
+    fn assert_read_csv_bytes(bytes: &[u8]) {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.csv");
+        std::fs::write(&file_path, bytes).unwrap();
+
+        let expected_df = df!(
+            "日付" => [Some(NaiveDate::from_ymd_opt(2025, 01, 27).unwrap()), Some(NaiveDate::from_ymd_opt(2025, 01, 28).unwrap())],
+            "体調" => [None, Some(3i32)],
+            "コメント" => [None, Some("テストコメント①髙")]
+        )
+        .unwrap();
+
+        let df = read_csv(Some(file_path)).unwrap();
+        temp_dir.close().unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
+    #[test]
+    fn test_read_csv_utf8_without_bom() {
+        assert_read_csv_bytes(RHYTHM_CARE_CSV.as_bytes());
+    }
+
+    #[test]
+    fn test_read_csv_shift_jis() {
+        // ①と髙はCP932の拡張文字
+        let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(RHYTHM_CARE_CSV);
+        assert!(!had_errors);
+        assert_read_csv_bytes(&bytes);
+    }
+
+    #[test]
+    fn test_read_csv_utf16_with_bom() {
+        let mut le_bytes = vec![0xFF, 0xFE];
+        let mut be_bytes = vec![0xFE, 0xFF];
+        for unit in RHYTHM_CARE_CSV.encode_utf16() {
+            le_bytes.extend(unit.to_le_bytes());
+            be_bytes.extend(unit.to_be_bytes());
+        }
+        assert_read_csv_bytes(&le_bytes);
+        assert_read_csv_bytes(&be_bytes);
+    }
+
+    #[test]
+    fn test_read_csv_missing_file() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("missing.csv");
+
+        let err = read_csv(Some(file_path.clone())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FileNotFound);
+    }
+
+    fn write_rhythm_care_csv(path: &Path, rows: &[&str]) {
+        let mut bfw = BufWriter::new(File::create(path).unwrap());
+        bfw.write_all(&[0xEF, 0xBB, 0xBF]).unwrap();
+        bfw.write_all("日付,\"愛さん体調\",\"愛さん体調\"\n".as_bytes())
+            .unwrap();
+        bfw.write_all(",\"\",コメント\n".as_bytes()).unwrap();
+        for row in rows {
+            bfw.write_all(format!("{row}\n").as_bytes()).unwrap();
+        }
+        bfw.flush().unwrap();
+    }
+
+    #[test]
+    fn test_load_condition_data_without_excel() {
+        let temp_dir = tempdir().unwrap();
+        let first_csv = temp_dir.path().join("first.csv");
+        let second_csv = temp_dir.path().join("second.csv");
+        write_rhythm_care_csv(&first_csv, &["2025/01/27,2,first", "2025/01/28,3,first"]);
+        write_rhythm_care_csv(&second_csv, &["2025/01/28,4,second", "2025/01/29,5,second"]);
+
+        let df = load_condition_data(
+            &[first_csv, second_csv],
+            None::<&Path>,
+            &LoadOptions::default(),
+        )
+        .unwrap()
+        .ldf
+        .collect()
+        .unwrap();
+        temp_dir.close().unwrap();
+
+        let expected_df = df!(
+            "日付" => [
+                NaiveDate::from_ymd_opt(2025, 1, 27).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 28).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 29).unwrap(),
+            ],
+            "体調" => [2i32, 4, 5],
+            "コメント" => ["first", "second", "second"]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
+    #[test]
+    fn test_create_report_reports_progress() {
+        let temp_dir = tempdir().unwrap();
+        let csv_path = temp_dir.path().join("data.csv");
+        let save_path = temp_dir.path().join("report.xlsx");
+        write_rhythm_care_csv(&csv_path, &["2024/12/31,2,", "2025/01/01,3,"]);
+        let csv_paths = [csv_path.to_string_lossy().to_string()];
+
+        let stages = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let progress = Progress::new({
+            let stages = stages.clone();
+            move |p| stages.lock().unwrap().push(p.clone())
+        });
+        create_report(
+            None,
+            &csv_paths,
+            None,
+            save_path.to_str().unwrap(),
+            vec![],
+            &LoadOptions::default(),
+            &SheetOptions::default(),
+            &ReportConfig::default(),
+            false,
+            &progress,
+        )
+        .unwrap();
+        let sheet = |year, index| ReportProgress::Sheet {
+            year,
+            person: None,
+            index,
+            total: 2,
+        };
+        assert_eq!(
+            *stages.lock().unwrap(),
+            vec![
+                ReportProgress::Parsing {
+                    path: csv_path.clone(),
+                    index: 1,
+                    total: 1,
+                },
+                ReportProgress::Merging { index: 1, total: 1 },
+                sheet(2024, 1),
+                sheet(2025, 2),
+                ReportProgress::Saving,
+            ]
+        );
+
+        // 中断を指示すると次の段階に進まない
+        let cancel_token = CancelToken::new();
+        cancel_token.cancel();
+        let err = create_report(
+            None,
+            &csv_paths,
+            None,
+            save_path.to_str().unwrap(),
+            vec![],
+            &LoadOptions::default(),
+            &SheetOptions::default(),
+            &ReportConfig::default(),
+            false,
+            &Progress::default().with_cancel_token(cancel_token),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_condition_data_reports_diagnostics() {
+        let temp_dir = tempdir().unwrap();
+        let csv_path = temp_dir.path().join("data.csv");
+        write_rhythm_care_csv(&csv_path, &["2025/01/27,7,", "2025/01/27,3,"]);
+
+        let data =
+            load_condition_data(&[&csv_path], None::<&Path>, &LoadOptions::default()).unwrap();
+        temp_dir.close().unwrap();
+
+        let found: Vec<(DiagnosticKind, Option<usize>)> =
+            data.diagnostics.iter().map(|d| (d.kind, d.row)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticKind::OutOfRange, Some(3)),
+                (DiagnosticKind::DuplicateDate, Some(4)),
+            ]
+        );
+        // 行番号の列は結合後のデータに残らない
+        let columns = data.ldf.collect().unwrap().get_column_names_owned();
+        assert!(!columns.iter().any(|c| c == ROW_COLUMN));
+    }
+
+    #[test]
+    fn test_read_excel_reports_unparseable_cells() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet().set_name("data").unwrap();
+        worksheet.write_string(0, 0, "日付").unwrap();
+        worksheet.write_string(0, 1, "体調").unwrap();
+        worksheet.write_string(0, 2, "コメント").unwrap();
+        worksheet.write_string(1, 0, "昨日").unwrap();
+        worksheet.write_string(1, 1, "よい").unwrap();
+        workbook.save(&file_path).unwrap();
+
+        let mut diagnostics = vec![];
+        let df = read_excel_with_rows(&file_path, DATA_SHEET, &mut diagnostics).unwrap();
+        temp_dir.close().unwrap();
+
+        assert_eq!(df.column("体調").unwrap().null_count(), 1);
+        let found: Vec<(&str, Option<&str>)> = diagnostics
+            .iter()
+            .map(|d| (d.column.as_str(), d.value.as_deref()))
+            .collect();
+        assert_eq!(found, vec![("日付", Some("昨日")), ("体調", Some("よい"))]);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::Unparseable && d.row == Some(2)));
+    }
+
+    #[test]
+    fn test_write_excel_skips_blank_dates() {
+        let test_df = df!(
+            "日付" => [NaiveDate::from_ymd_opt(2025, 1, 2), None],
+            "体調" => [Some(3i32), Some(4)],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .write(&test_df.lazy(), &file_path)
+            .unwrap();
+
+        let excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        assert!(!excel.sheet_names().iter().any(|name| name == "0"));
+        assert!(excel.sheet_names().iter().any(|name| name == "2025"));
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_excel_drops_unparseable_dates() {
+        let temp_dir = tempdir().unwrap();
+        let csv_path = temp_dir.path().join("data.csv");
+        let save_path = temp_dir.path().join("report.xlsx");
+        write_rhythm_care_csv(
+            &csv_path,
+            &[
+                "2025/01/01,3,",
+                "2025/13/01,4,a",
+                "2025/02/30,5,b",
+                "2025/01/02,2,",
+            ],
+        );
+        let summary = create_report(
+            None,
+            &[csv_path.to_string_lossy().to_string()],
+            None,
+            save_path.to_str().unwrap(),
+            vec![],
+            &LoadOptions::default(),
+            &SheetOptions::default(),
+            &ReportConfig::default(),
+            false,
+            &Progress::default(),
+        )
+        .unwrap();
+        let rows: Vec<Option<usize>> = summary.diagnostics.iter().map(|d| d.row).collect();
+        assert_eq!(rows, [Some(4), Some(5)]);
+        assert!(summary
+            .diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::Unparseable && d.column == "日付"));
+
+        // 日付の無い行が1行にまとめられてdataシートに残らない
+        let df = read_excel(&save_path).unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.column("日付").unwrap().null_count(), 0);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_condition_data_without_input() {
+        let err = load_condition_data(&[] as &[&str], None::<&str>, &LoadOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::NoInput);
+    }
+
+    #[test]
+    fn test_prepare_yearly_frame_with_holidays() {
+        let ldf = df!(
+            "日付" => [NaiveDate::from_ymd_opt(2025, 5, 6).unwrap()],
+            "体調" => [Some(3i32)],
+            "コメント" => [None::<&str>]
+        )
+        .unwrap()
+        .lazy();
+        let holidays = HolidayCalendar::new()
+            .with_custom_holidays([NaiveDate::from_ymd_opt(2025, 8, 13).unwrap()]);
+
+        let df = prepare_yearly_frame(&ldf, 2025, &holidays, &ScoreScale::default())
+            .unwrap()
+            .collect()
+            .unwrap();
+        let names: Vec<&str> = df.get_column_names().iter().map(|n| n.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "日付",
+                "体調",
+                "コメント",
+                "曜日",
+                "土日判定",
+                "祝日",
+                "7日平均",
+                "30日平均",
+                "前日差",
+                "低調連続日数"
+            ]
+        );
+
+        let flag_on = |month, day| {
+            let row = NaiveDate::from_ymd_opt(2025, month, day)
+                .unwrap()
+                .ordinal0() as usize;
+            (
+                df.column("土日判定").unwrap().i32().unwrap().get(row),
+                df.column("祝日").unwrap().str().unwrap().get(row),
+            )
+        };
+        // 振替休日
+        assert_eq!(flag_on(5, 6), (Some(5), Some("振替休日")));
+        // 利用者指定の休日
+        assert_eq!(flag_on(8, 13), (Some(5), Some("休日")));
+        // 土曜日
+        assert_eq!(flag_on(5, 10), (Some(5), None));
+        // 平日
+        assert_eq!(flag_on(5, 7), (Some(0), None));
+    }
+
+    #[test]
+    fn test_prepare_yearly_frame_trend_columns() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let ldf = df!(
+            "日付" => [date(1), date(2), date(3), date(4), date(6), date(7)],
+            "体調" => [4i32, 2, 1, 2, 1, 3],
+            "コメント" => [None::<&str>; 6]
+        )
+        .unwrap()
+        .lazy();
+
+        let df = prepare_yearly_frame(&ldf, 2025, &HolidayCalendar::new(), &ScoreScale::default())
+            .unwrap()
+            .slice(0, 7)
+            .collect()
+            .unwrap();
+        let weekly_mean: Vec<Option<f64>> = df
+            .column("7日平均")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            weekly_mean,
+            [
+                Some(4.0),
+                Some(3.0),
+                Some(7.0 / 3.0),
+                Some(2.25),
+                Some(2.25),
+                Some(2.0),
+                Some(13.0 / 6.0)
+            ]
+        );
+        let change: Vec<Option<i32>> = df
+            .column("前日差")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            change,
+            [None, Some(-2), Some(-1), Some(1), None, None, Some(2)]
+        );
+        // 記録の無い日(1/5)で連続は途切れる
+        let streak: Vec<Option<u32>> = df
+            .column("低調連続日数")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            streak,
+            [
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(0),
+                Some(1),
+                Some(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prepare_agg_table_summary_rows() {
+        // 1/31までの記録のうち1/2と1/3が未記録、2月は1日だけ記録
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let mut dates: Vec<NaiveDate> = (1..=31).map(|day| date(1, day)).collect();
+        let mut conditions: Vec<Option<i32>> = (1..=31).map(|day| Some(day % 2 + 2)).collect();
+        conditions[1] = None;
+        conditions[2] = None;
+        dates.push(date(2, 1));
+        conditions.push(Some(5));
+        let ldf = df!(
+            "日付" => dates,
+            "体調" => conditions,
+            "コメント" => [None::<&str>; 32]
+        )
+        .unwrap()
+        .lazy();
+        let yearly_ldf =
+            prepare_yearly_frame(&ldf, 2025, &HolidayCalendar::new(), &ScoreScale::default())
+                .unwrap();
+
+        let df = prepare_agg_table(&yearly_ldf, &ScoreScale::default())
+            .unwrap()
+            .collect()
+            .unwrap();
+        let labels: Vec<Option<&str>> = df.column("調子").unwrap().str().unwrap().iter().collect();
+        assert_eq!(
+            labels[6..],
+            [
+                Some("平均"),
+                Some("中央値"),
+                Some("標準偏差"),
+                Some("記録日数"),
+                Some("未記録日数")
+            ]
+        );
+        let value = |name: &str, row: usize| df.column(name).unwrap().f64().unwrap().get(row);
+        // 1月は3が15日、2が14日
+        assert_eq!(value("1月", 6), Some(2.52));
+        assert_eq!(value("1月", 7), Some(3.0));
+        assert_eq!(value("1月", 9), Some(29.0));
+        assert_eq!(value("1月", 10), Some(2.0));
+        assert_eq!(value("2月", 9), Some(1.0));
+        assert_eq!(value("2月", 10), Some(0.0));
+        assert_eq!(value("3月", 6), None);
+        assert_eq!(value("年間", 9), Some(30.0));
+        // 体調毎の日数の行はそのまま残る
+        assert_eq!(value("1月", 2), Some(15.0));
+    }
+
+    #[test]
+    fn test_prepare_agg_table_custom_scale() {
+        let scale = ScoreScale {
+            min: 1,
+            max: 10,
+            low_threshold: 3,
+            axis_min: None,
+            levels: (1..=10)
+                .rev()
+                .map(|value| ScoreLevel {
+                    value,
+                    label: value.to_string(),
+                    color: None,
+                })
+                .collect(),
+        };
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let ldf = df!(
+            "日付" => [date(1), date(2), date(3)],
+            "体調" => [Some(10), Some(7), Some(7)],
+            "コメント" => [None::<&str>; 3]
+        )
+        .unwrap()
+        .lazy();
+        let yearly_ldf = prepare_yearly_frame(&ldf, 2025, &HolidayCalendar::new(), &scale).unwrap();
+
+        let df = prepare_agg_table(&yearly_ldf, &scale)
+            .unwrap()
+            .collect()
+            .unwrap();
+        // 10段階の行と集計の5行
+        assert_eq!(df.height(), 15);
+        let labels: Vec<Option<&str>> = df.column("調子").unwrap().str().unwrap().iter().collect();
+        assert_eq!(labels[0], Some("10"));
+        assert_eq!(labels[3], Some("7"));
+        let value = |row: usize| df.column("1月").unwrap().f64().unwrap().get(row);
+        assert_eq!(value(0), Some(1.0));
+        assert_eq!(value(3), Some(2.0));
+        assert_eq!(value(10), Some(8.0));
+    }
+
+    #[test]
+    fn test_prepare_weekday_frame() {
+        // 2025/1/4(土)〜1/7(火)、1/6は祝日
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let ldf = df!(
+            "日付" => [date(4), date(5), date(6), date(7)],
+            "体調" => [Some(5i32), Some(2), Some(4), None],
+            "コメント" => [None::<&str>; 4]
+        )
+        .unwrap()
+        .lazy();
+        let holidays = HolidayCalendar::new().with_custom_holidays([date(6)]);
+        let yearly_ldf =
+            prepare_yearly_frame(&ldf, 2025, &holidays, &ScoreScale::default()).unwrap();
+
+        let df = prepare_weekday_frame(&yearly_ldf, &ScoreScale::default())
+            .unwrap()
+            .collect()
+            .unwrap();
+        let names: Vec<&str> = df.get_column_names_str();
+        assert_eq!(
+            names,
+            [
+                "区分",
+                "平均",
+                "中央値",
+                "記録日数",
+                "↑5",
+                "↗4",
+                "→3",
+                "↘2",
+                "↓1",
+                "⇓0"
+            ]
+        );
+        let row = |label: &str| {
+            let i = df
+                .column("区分")
+                .unwrap()
+                .str()
+                .unwrap()
+                .iter()
+                .position(|v| v == Some(label))
+                .unwrap();
+            (
+                df.column("平均").unwrap().f64().unwrap().get(i),
+                df.column("記録日数").unwrap().u32().unwrap().get(i),
+                df.column("↗4").unwrap().u32().unwrap().get(i),
+            )
+        };
+        assert_eq!(row("土"), (Some(5.0), Some(1), Some(0)));
+        assert_eq!(row("火"), (None, Some(0), Some(0)));
+        assert_eq!(row("土日祝"), (Some(11.0 / 3.0), Some(3), Some(1)));
+        assert_eq!(row("平日"), (None, Some(0), Some(0)));
+        assert_eq!(
+            df.column("区分").unwrap().str().unwrap().get(8),
+            Some("土日祝")
+        );
+    }
+
+    #[test]
+    fn test_prepare_missing_frames() {
+        // 1/3〜1/4と1/7、1/11〜1/31が未記録で、2/1が最後の記録
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let recorded = [1, 2, 5, 6, 8, 9, 10];
+        let ldf = df!(
+            "日付" => recorded
+                .iter()
+                .map(|&day| date(1, day))
+                .chain([date(2, 1)])
+                .collect::<Vec<_>>(),
+            "体調" => [3i32; 8],
+            "コメント" => [None::<&str>; 8]
+        )
+        .unwrap()
+        .lazy();
+        let yearly_ldf =
+            prepare_yearly_frame(&ldf, 2025, &HolidayCalendar::new(), &ScoreScale::default())
+                .unwrap();
+
+        let ranges_df = prepare_missing_ranges_frame(&yearly_ldf).collect().unwrap();
+        let starts: Vec<Option<i32>> = ranges_df
+            .column("開始日")
+            .unwrap()
+            .date()
+            .unwrap()
+            .into_iter()
+            .collect();
+        let epoch_days = |month, day| {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+            Some(date(month, day).signed_duration_since(epoch).num_days() as i32)
+        };
+        assert_eq!(
+            starts,
+            [epoch_days(1, 3), epoch_days(1, 7), epoch_days(1, 11)]
+        );
+        let days: Vec<Option<u32>> = ranges_df
+            .column("日数")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .iter()
+            .collect();
+        assert_eq!(days, [Some(2), Some(1), Some(21)]);
+
+        let df = prepare_completeness_frame(&yearly_ldf)
+            .unwrap()
+            .collect()
+            .unwrap();
+        let months: Vec<Option<&str>> = df.column("月").unwrap().str().unwrap().iter().collect();
+        assert_eq!(months, [Some("1月"), Some("2月"), Some("年間")]);
+        let value = |name: &str| -> Vec<Option<u32>> {
+            df.column(name).unwrap().u32().unwrap().iter().collect()
+        };
+        assert_eq!(value("日数"), [Some(31), Some(1), Some(32)]);
+        assert_eq!(value("記録日数"), [Some(7), Some(1), Some(8)]);
+        assert_eq!(value("未記録日数"), [Some(24), Some(0), Some(24)]);
+        let rates: Vec<Option<f64>> = df.column("記録率").unwrap().f64().unwrap().iter().collect();
+        assert_eq!(rates, [Some(22.6), Some(100.0), Some(25.0)]);
+
+        // 体調の記録が無い年は記録率を空欄にする
+        let empty_ldf = df!(
+            "日付" => [date(1, 1), date(1, 2)],
+            "体調" => [None::<i32>; 2],
+            "コメント" => [None::<&str>; 2]
+        )
+        .unwrap()
+        .lazy();
+        let yearly_ldf = prepare_yearly_frame(
+            &empty_ldf,
+            2025,
+            &HolidayCalendar::new(),
+            &ScoreScale::default(),
+        )
+        .unwrap();
+        let df = prepare_completeness_frame(&yearly_ldf)
+            .unwrap()
+            .collect()
+            .unwrap();
+        assert_eq!(df.height(), 1);
+        assert_eq!(df.column("日数").unwrap().u32().unwrap().get(0), Some(0));
+        assert_eq!(df.column("記録率").unwrap().f64().unwrap().get(0), None);
+    }
+
+    #[test]
+    fn test_read_excel_by_header_with_extra_columns() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        let mut workbook = Workbook::new();
+        let date_format = Format::new().set_num_format("yyyy/mm/dd");
+        let worksheet = workbook.add_worksheet().set_name("data").unwrap();
+        // 列の順番を入れ替え、利用者が追加した列と見出しが重なる列を含める
+        worksheet.write_string(0, 0, "コメント").unwrap();
+        worksheet.write_string(0, 1, "日付").unwrap();
+        worksheet.write_string(0, 2, "睡眠時間").unwrap();
+        worksheet.write_string(0, 3, "体調").unwrap();
+        worksheet.write_string(0, 4, "服薬").unwrap();
+        worksheet.write_string(0, 5, "睡眠時間").unwrap();
+        worksheet.write_string(0, 6, "体調").unwrap();
+        worksheet
+            .write_string(1, 0, "Test comment")
+            .unwrap()
+            .write_with_format(
+                1,
+                1,
+                ExcelDateTime::from_ymd(2025, 1, 25).unwrap(),
+                &date_format,
+            )
+            .unwrap()
+            .write_number(1, 2, 7.5)
+            .unwrap()
+            .write_number(1, 3, 4)
+            .unwrap()
+            .write_string(1, 4, "頭痛薬")
+            .unwrap()
+            .write_number(1, 5, 6.0)
+            .unwrap()
+            .write_number(1, 6, 1)
+            .unwrap();
+        workbook.save(&file_path).unwrap();
+
+        let df = read_excel(&file_path).unwrap();
+        temp_dir.close().unwrap();
+
+        let expected_df = df!(
+            "日付" => [NaiveDate::from_ymd_opt(2025, 1, 25).unwrap()],
+            "体調" => [4i32],
+            "コメント" => ["Test comment"],
+            "睡眠時間" => [7.5f64],
+            "服薬" => ["頭痛薬"],
+            "睡眠時間_2" => [6.0f64],
+            "体調_2" => [1.0f64]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
+    #[test]
+    fn test_read_excel_without_condition_column() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet().set_name("data").unwrap();
+        worksheet.write_string(0, 0, "日付").unwrap();
+        worksheet.write_string(0, 1, "コメント").unwrap();
+        workbook.save(&file_path).unwrap();
+
+        let err = read_excel(&file_path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ColumnNotFound);
+        assert_eq!(serde_json::to_value(&err).unwrap()["column"], "体調");
+    }
+
+    #[test]
+    fn test_merge_condition_df_keeps_extra_columns() {
+        let csv_df = df!(
+            "日付" => [NaiveDate::from_ymd_opt(2025, 1, 26).unwrap(), NaiveDate::from_ymd_opt(2025, 1, 27).unwrap()],
+            "体調" => [Some(4i32), Some(3i32)],
+            "コメント" => [None, Some("csv")]
+        )
+        .unwrap();
+        let excel_df = df!(
+            "日付" => [NaiveDate::from_ymd_opt(2025, 1, 25).unwrap(), NaiveDate::from_ymd_opt(2025, 1, 26).unwrap()],
+            "体調" => [Some(2i32), Some(2i32)],
+            "コメント" => [Some("excel"), Some("excel")],
+            "睡眠時間" => [Some(6.0f64), Some(7.5f64)]
+        )
+        .unwrap();
+
+        let df = merge_condition_data(&csv_df, &excel_df)
+            .unwrap()
+            .collect()
+            .unwrap();
+
+        let expected_df = df!(
+            "日付" => [
+                NaiveDate::from_ymd_opt(2025, 1, 25).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 26).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 27).unwrap(),
+            ],
+            "体調" => [Some(2i32), Some(4i32), Some(3i32)],
+            "コメント" => [Some("excel"), None, Some("csv")],
+            "睡眠時間" => [Some(6.0f64), Some(7.5f64), None]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+    }
+
     #[test]
     fn test_merge_condition_df() {
         let csv_df = df!(
@@ -610,7 +3143,7 @@ mod tests {
             "コメント" => [None, Some("テストコメント"), None, Some("Test comment")]
         )
         .unwrap();
-        let ldf = merge_condition_data(&csv_df, &excel_df);
+        let ldf = merge_condition_data(&csv_df, &excel_df).unwrap();
         print!("ldf: {:?}", ldf.clone().collect().unwrap());
         println!("expected_df: {:?}", expected_df);
         assert!(ldf.collect().unwrap().equals_missing(&expected_df));
@@ -643,12 +3176,410 @@ mod tests {
             Err(e) => panic!("Failed to write Excel file: {}", e),
         }
     }
+    #[test]
+    fn test_write_year_comparison_tables() {
+        let test_df = df!(
+            "日付" => [
+                NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            ],
+            "体調" => [5i32, 2, 3],
+            "コメント" => [None::<&str>, None, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .write(&test_df.lazy(), &file_path)
+            .unwrap();
+
+        let mut excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        let range = excel.worksheet_range("年間体調比較").unwrap();
+        let cell = |row, col| range.get_value((row, col)).cloned();
+        // 月平均の表 (16列目から)
+        assert_eq!(cell(0, 16), Some(Data::String("月".into())));
+        assert_eq!(cell(0, 17), Some(Data::String("2024年".into())));
+        assert_eq!(cell(0, 18), Some(Data::String("2025年".into())));
+        assert_eq!(cell(1, 17), Some(Data::Float(2.5)));
+        assert_eq!(cell(1, 18), Some(Data::Float(5.0)));
+        // 体調の分布の表 (月平均の表の右に1列空ける)
+        assert_eq!(cell(0, 20), Some(Data::String("年".into())));
+        assert_eq!(cell(0, 21), Some(Data::String("↑5".into())));
+        assert_eq!(cell(2, 21), Some(Data::Float(1.0)));
+        assert_eq!(cell(1, 24), Some(Data::Float(1.0)));
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_with_report_config() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(25), date(25)],
+            "人" => ["愛さん", "太郎さん"],
+            "体調" => [2i32, 4],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let mut config = ReportConfig::default();
+        config.sheet_names.data = "記録".into();
+        config.sheet_names.comparison = "比較".into();
+        config.sheet_names.missing_days = "未記録".into();
+        config.sections.weekday_analysis = false;
+        config.sections.person_comparison = false;
+        config.sections.highlight_missing_days = true;
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .set_config(config)
+            .write(&test_df.lazy(), &file_path)
+            .unwrap();
+
+        let excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        assert_eq!(
+            excel.sheet_names(),
+            [
+                "記録",
+                "比較_太郎さん",
+                "2025_太郎さん",
+                "未記録_太郎さん",
+                "比較_愛さん",
+                "2025_愛さん",
+                "未記録_愛さん"
+            ]
+        );
+        // 設定したdataシートの名前で読み込み直せる
+        let mut diagnostics = vec![];
+        let df = read_excel_with_rows(&file_path, "記録", &mut diagnostics).unwrap();
+        assert_eq!(df.height(), 2);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_with_english_headers() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(25), date(26)],
+            "体調" => [2i32, 4],
+            "コメント" => [Some("test"), None],
+            "睡眠" => [7.5, 6.0]
+        )
+        .unwrap();
+        let mut config = ReportConfig::default();
+        config.locale = Locale::En;
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .set_config(config)
+            .write(&test_df.clone().lazy(), &file_path)
+            .unwrap();
+
+        let mut excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        let range = excel.worksheet_range("2025").unwrap();
+        let headers: Vec<String> = range.rows().next().unwrap()[..10]
+            .iter()
+            .map(|cell| cell.to_string())
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                "Date",
+                "Condition",
+                "Comment",
+                "Weekday",
+                "Day off",
+                "Holiday",
+                "7-day mean",
+                "30-day mean",
+                "Change",
+                "Low streak"
+            ]
+        );
+        // 英語の見出しのdataシートも前回のデータとして読み込める
+        assert_eq!(read_excel(&file_path).unwrap(), test_df);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_english_report() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(6), date(6), date(8)],
+            "人" => [Some("愛さん"), None, Some("愛さん")],
+            "体調" => [2i32, 4, 3],
+            "コメント" => [None::<&str>, None, None]
+        )
+        .unwrap();
+        let mut config = ReportConfig::default();
+        config.locale = Locale::En;
+        config.sheet_names.missing_days = "未記録".into();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .set_config(config)
+            .write(&test_df.lazy(), &file_path)
+            .unwrap();
+
+        let mut excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        // 変更したシート名はそのまま、既定のシート名は英語にする
+        assert_eq!(
+            excel.sheet_names(),
+            [
+                "data",
+                "Yearly comparison_愛さん",
+                "2025_愛さん",
+                "Weekday analysis_愛さん",
+                "未記録_愛さん",
+                "Yearly comparison_Unassigned",
+                "2025_Unassigned",
+                "Weekday analysis_Unassigned",
+                "未記録_Unassigned",
+                "Person comparison"
+            ]
+        );
+        let range = excel.worksheet_range("Weekday analysis_愛さん").unwrap();
+        let cell = |row, col| range.get_value((row, col)).cloned();
+        assert_eq!(cell(0, 0), Some(Data::String("2025".into())));
+        assert_eq!(cell(1, 0), Some(Data::String("Group".into())));
+        assert_eq!(cell(2, 0), Some(Data::String("Mon".into())));
+        assert_eq!(cell(2, 1), Some(Data::Float(2.0)));
+        assert_eq!(
+            cell(10, 0),
+            Some(Data::String("Weekends & holidays".into()))
+        );
+
+        let range = excel.worksheet_range("2025_愛さん").unwrap();
+        let cell = |row, col| range.get_value((row, col)).cloned();
+        assert_eq!(cell(0, 3), Some(Data::String("Weekday".into())));
+        // 1月6日(成人の日)は月曜日
+        assert_eq!(cell(6, 3), Some(Data::String("Mon".into())));
+        assert_eq!(cell(0, 11), Some(Data::String("Level".into())));
+        assert_eq!(cell(0, 13), Some(Data::String("Annual".into())));
+        assert_eq!(cell(0, 14), Some(Data::String("Jan".into())));
+        assert_eq!(cell(7, 11), Some(Data::String("Mean".into())));
+
+        let range = excel.worksheet_range("Person comparison").unwrap();
+        let cell = |row, col| range.get_value((row, col)).cloned();
+        assert_eq!(cell(1, 0), Some(Data::String("Person".into())));
+        assert_eq!(cell(3, 0), Some(Data::String("Unassigned".into())));
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_update_rewrites_changed_years() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let previous_df = df!(
+            "日付" => [date(2024, 12, 31), date(2025, 1, 1)],
+            "体調" => [3i32, 4],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("体調管理.xlsx");
+        ConditionWorkbook::new()
+            .write(&previous_df.clone().lazy(), &file_path)
+            .unwrap();
+        // 利用者が追加したシート
+        let memo_path = temp_dir.path().join("memo.xlsx");
+        let mut memo = rust_xlsxwriter::Workbook::new();
+        memo.add_worksheet()
+            .set_name("メモ")
+            .unwrap()
+            .write_string(0, 0, "通院の記録")
+            .unwrap();
+        memo.save(&memo_path).unwrap();
+        update::carry_over_sheets(&memo_path, &file_path, &file_path, |_| true).unwrap();
+
+        let merged_df = df!(
+            "日付" => [date(2024, 12, 31), date(2025, 1, 1), date(2025, 1, 2)],
+            "体調" => [3i32, 4, 5],
+            "コメント" => [None::<&str>, None, None]
+        )
+        .unwrap();
+        let summary = ConditionWorkbook::new()
+            .update(&merged_df.lazy(), &file_path, &file_path)
+            .unwrap();
+        assert_eq!(summary.years, [2025]);
+        assert_eq!(summary.carried_sheets, ["2024", "メモ"]);
+
+        let mut excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        assert_eq!(
+            excel.sheet_names(),
+            [
+                "data",
+                "年間体調比較",
+                "2024",
+                "2025",
+                "曜日別分析",
+                "未記録日",
+                "メモ"
+            ]
+        );
+        let memo = excel.worksheet_range("メモ").unwrap();
+        assert_eq!(
+            memo.get_value((0, 0)),
+            Some(&Data::String("通院の記録".into()))
+        );
+        assert_eq!(read_excel(&file_path).unwrap().height(), 3);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_update_rebuilds_all_years_when_config_changes() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(2024, 12, 31), date(2025, 1, 1)],
+            "体調" => [3i32, 4],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("体調管理.xlsx");
+        ConditionWorkbook::new()
+            .write(&test_df.clone().lazy(), &file_path)
+            .unwrap();
+
+        // データも設定も変わらなければ作り直さない
+        let summary = ConditionWorkbook::new()
+            .update(&test_df.clone().lazy(), &file_path, &file_path)
+            .unwrap();
+        assert!(summary.years.is_empty());
+
+        let mut config = ReportConfig::default();
+        config.colors.holiday_fill = "#DDEBF7".into();
+        let summary = ConditionWorkbook::new()
+            .set_config(config.clone())
+            .update(&test_df.clone().lazy(), &file_path, &file_path)
+            .unwrap();
+        assert_eq!(summary.years, [2024, 2025]);
+
+        // 休日を追加した場合も作り直す
+        let summary = ConditionWorkbook::new()
+            .set_config(config)
+            .set_holidays(HolidayCalendar::new().with_custom_holidays([date(2025, 1, 6)]))
+            .update(&test_df.lazy(), &file_path, &file_path)
+            .unwrap();
+        assert_eq!(summary.years, [2024, 2025]);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_update_adds_missing_years_and_drops_stale_sheets() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(2024, 12, 31), date(2025, 1, 1)],
+            "体調" => [3i32, 4],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("体調管理.xlsx");
+        // dataシートとconflictsシートだけのファイルに、利用者がシートを追加した
+        let conflict = MergeConflict {
+            date: date(2025, 1, 1),
+            person: None,
+            column: "体調".into(),
+            excel: Some("3".into()),
+            csv: Some("4".into()),
+            resolved: Some("4".into()),
+        };
+        ConditionWorkbook::new()
+            .set_conflicts(vec![conflict])
+            .write_data(&test_df.clone().lazy(), &file_path)
+            .unwrap();
+        let memo_path = temp_dir.path().join("memo.xlsx");
+        let mut memo = rust_xlsxwriter::Workbook::new();
+        memo.add_worksheet().set_name("2025メモ").unwrap();
+        memo.save(&memo_path).unwrap();
+        update::carry_over_sheets(&memo_path, &file_path, &file_path, |_| true).unwrap();
+
+        let mut config = ReportConfig::default();
+        config.sections.weekday_analysis = false;
+        config.sections.missing_days = false;
+        let summary = ConditionWorkbook::new()
+            .set_config(config)
+            .update(&test_df.lazy(), &file_path, &file_path)
+            .unwrap();
+        assert_eq!(summary.years, [2024, 2025]);
+        assert_eq!(summary.carried_sheets, ["2025メモ"]);
+
+        let excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        assert_eq!(
+            excel.sheet_names(),
+            ["data", "年間体調比較", "2024", "2025", "2025メモ"]
+        );
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_excel_per_person() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let test_df = df!(
+            "日付" => [date(25), date(25), date(26)],
+            "人" => [Some("愛さん"), Some("太郎さん"), None],
+            "体調" => [2i32, 4, 3],
+            "コメント" => [None::<&str>, None, None]
+        )
+        .unwrap();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.xlsx");
+        ConditionWorkbook::new()
+            .write(&test_df.lazy(), &file_path)
+            .unwrap();
+
+        let excel: Xlsx<_> = open_workbook(&file_path).unwrap();
+        assert_eq!(
+            excel.sheet_names(),
+            [
+                "data",
+                "年間体調比較_太郎さん",
+                "2025_太郎さん",
+                "曜日別分析_太郎さん",
+                "未記録日_太郎さん",
+                "年間体調比較_愛さん",
+                "2025_愛さん",
+                "曜日別分析_愛さん",
+                "未記録日_愛さん",
+                "年間体調比較_未設定",
+                "2025_未設定",
+                "曜日別分析_未設定",
+                "未記録日_未設定",
+                "人別比較",
+            ]
+        );
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_person_sheet_name() {
+        assert_eq!(person_sheet_name("2025", None), "2025");
+        assert_eq!(person_sheet_name("2025", Some("愛/さん")), "2025_愛_さん");
+        // 31文字を超える名前は先頭が同じでも別のシート名にする
+        let long = |suffix| format!("{}{suffix}", "あ".repeat(30));
+        let first = person_sheet_name("2025", Some(&long("い")));
+        let second = person_sheet_name("2025", Some(&long("う")));
+        assert_eq!(first.chars().count(), 31);
+        assert_ne!(first, second);
+        assert_eq!(first, person_sheet_name("2025", Some(&long("い"))));
+    }
+
     #[test]
     fn test_write_excel_with_csv() {
         let csv_path = "../test_data/RhythmCareData.csv";
         let excel_path = "../test_data/体調記録_Sean_20250331.xlsx";
         let save_path = "../test_data/test.xlsx";
-        match write_excel(csv_path, excel_path, save_path) {
+        match create_report(
+            None,
+            &[csv_path.into()],
+            Some(excel_path),
+            save_path,
+            vec![],
+            &LoadOptions::default(),
+            &SheetOptions::default(),
+            &ReportConfig::default(),
+            false,
+            &Progress::default(),
+        ) {
             Ok(_) => assert!(true),
             Err(e) => panic!("Failed to write Excel file: {}", e),
         }
@@ -0,0 +1,275 @@
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{localize_headers, ConditionColumn, WEEKDAY_COLUMN};
+
+/// レポートの言語 (シート名・見出し・曜日・グラフのタイトル)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+/// 集計表などの見出しと値、グラフのタイトルの英語の表記
+///
+/// 「N月」「N年」は決まった形なので`Locale::translate`で個別に変換する。
+const ENGLISH_TEXTS: [(&str, &str); 38] = [
+    ("月", "Month"),
+    ("年", "Year"),
+    ("年間", "Annual"),
+    ("全期間", "All years"),
+    ("調子", "Level"),
+    ("区分", "Group"),
+    ("平日", "Weekdays"),
+    ("土日祝", "Weekends & holidays"),
+    ("平均", "Mean"),
+    ("中央値", "Median"),
+    ("標準偏差", "Std dev"),
+    ("記録日数", "Recorded days"),
+    ("未記録日数", "Missing days"),
+    ("日数", "Days"),
+    ("記録率", "Recorded %"),
+    ("開始日", "From"),
+    ("終了日", "To"),
+    ("未設定", "Unassigned"),
+    ("列", "Column"),
+    ("結合前", "Before merge"),
+    ("採用した値", "Resolved"),
+    ("ファイル", "File"),
+    ("行", "Row"),
+    ("種類", "Kind"),
+    ("値", "Value"),
+    ("範囲外の値", "Out of range"),
+    ("未来の日付", "Future date"),
+    ("重複した日付", "Duplicate date"),
+    ("読み取れない値", "Unparseable value"),
+    ("月平均の体調", "Monthly mean condition"),
+    ("月平均の体調 (折れ線)", "Monthly mean condition (line)"),
+    ("月平均の体調 (縦棒)", "Monthly mean condition (column)"),
+    ("年間の体調の分布", "Condition distribution by year"),
+    ("年間体調比較", "Yearly comparison"),
+    ("曜日別分析", "Weekday analysis"),
+    ("未記録日", "Missing days"),
+    ("人別比較", "Person comparison"),
+    ("検証結果", "Diagnostics"),
+];
+
+/// 曜日の表記 (月曜日から)
+const WEEKDAYS: [(&str, &str); 7] = [
+    ("月", "Mon"),
+    ("火", "Tue"),
+    ("水", "Wed"),
+    ("木", "Thu"),
+    ("金", "Fri"),
+    ("土", "Sat"),
+    ("日", "Sun"),
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// 値を表記する言語に変える列 (それ以外の列は利用者の入力した値なのでそのまま書き出す)
+const TRANSLATED_COLUMNS: [&str; 8] = [
+    WEEKDAY_COLUMN,
+    "区分",
+    "調子",
+    "月",
+    "年",
+    "人",
+    "種類",
+    "列",
+];
+
+impl Locale {
+    /// 日本語の表記をこの言語にする (対応する表記が無ければそのまま)
+    pub(crate) fn text(self, ja: &'static str) -> &'static str {
+        match self {
+            Self::Ja => ja,
+            Self::En => ENGLISH_TEXTS
+                .iter()
+                .find(|(text, _)| *text == ja)
+                .map_or(ja, |(_, en)| en),
+        }
+    }
+
+    /// 年の見出し (「2025年」)
+    pub(crate) fn year(self, year: i32) -> String {
+        match self {
+            Self::Ja => format!("{year}年"),
+            Self::En => year.to_string(),
+        }
+    }
+
+    /// 月毎のグラフのタイトル (「1月」)
+    pub(crate) fn month_title(self, month: i32) -> String {
+        match self {
+            Self::Ja => format!("{month}月"),
+            Self::En => MONTHS[(month - 1) as usize].into(),
+        }
+    }
+
+    /// 集計表の見出しや値を英語にする (「N月」は月の略称、「N年」は年の数字)
+    fn translate(self, ja: &str) -> Option<String> {
+        if self == Self::Ja {
+            return None;
+        }
+        if let Some((_, en)) = ENGLISH_TEXTS.iter().find(|(text, _)| *text == ja) {
+            return Some(en.to_string());
+        }
+        if let Some(column) = ConditionColumn::from_label(ja) {
+            return Some(column.label(self).into());
+        }
+        if let Some(month) = ja.strip_suffix('月').and_then(|m| m.parse::<usize>().ok()) {
+            return MONTHS
+                .get(month.wrapping_sub(1))
+                .map(|name| name[..3].to_string());
+        }
+        ja.strip_suffix('年')
+            .filter(|year| year.parse::<i32>().is_ok())
+            .map(String::from)
+    }
+}
+
+/// 集計表などレポートが作成する表の見出しと、曜日や集計の区分などの値をこの言語にする
+///
+/// 利用者が追加した列の見出しやコメントは変えない。
+pub(crate) fn localize_frame(df: &DataFrame, locale: Locale) -> PolarsResult<DataFrame> {
+    if locale == Locale::Ja {
+        return Ok(df.clone());
+    }
+    let columns = df
+        .get_columns()
+        .iter()
+        .map(|column| {
+            let name = column.name().as_str();
+            let mut column = match TRANSLATED_COLUMNS.contains(&name) {
+                true => translate_values(column, locale)?,
+                false => column.clone(),
+            };
+            if let Some(label) = locale.translate(name) {
+                column.rename(label.into());
+            }
+            Ok(column)
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    DataFrame::new(columns)
+}
+
+/// 年毎のシートの日毎のデータの見出しと曜日をこの言語にする
+///
+/// 日毎のデータは利用者の入力した値なので、レポートが作成する曜日の列の他は値を変えない。
+pub(crate) fn localize_records(df: &DataFrame, locale: Locale) -> PolarsResult<DataFrame> {
+    let mut df = localize_headers(df, locale)?;
+    if locale == Locale::Ja {
+        return Ok(df);
+    }
+    let weekday = ConditionColumn::Weekday.label(locale);
+    if let Ok(column) = df.column(weekday) {
+        let column = translate_values(column, locale)?;
+        df.with_column(column)?;
+    }
+    Ok(df)
+}
+
+/// 列の値をこの言語にする (文字列でない列はそのまま)
+fn translate_values(column: &Column, locale: Locale) -> PolarsResult<Column> {
+    let DataType::String = column.dtype() else {
+        return Ok(column.clone());
+    };
+    // 「月」は曜日の列と区分の列でのみ月曜日を表す
+    let name = column.name().as_str();
+    let weekdays = [
+        WEEKDAY_COLUMN,
+        ConditionColumn::Weekday.label(locale),
+        "区分",
+    ]
+    .contains(&name);
+    let values: Vec<Option<String>> = column
+        .str()?
+        .iter()
+        .map(|value| {
+            value.map(
+                |value| match WEEKDAYS.iter().find(|(ja, _)| weekdays && *ja == value) {
+                    Some((_, en)) => en.to_string(),
+                    None => locale.translate(value).unwrap_or_else(|| value.into()),
+                },
+            )
+        })
+        .collect();
+    Ok(Column::new(column.name().clone(), values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize_frame() {
+        let df = df!(
+            "区分" => ["月", "土日祝"],
+            "1月" => [3.0, 4.0],
+            "コメント" => ["平均", "月"]
+        )
+        .unwrap();
+        assert_eq!(localize_frame(&df, Locale::Ja).unwrap(), df);
+
+        let en_df = localize_frame(&df, Locale::En).unwrap();
+        let expected = df!(
+            "Group" => ["Mon", "Weekends & holidays"],
+            "Jan" => [3.0, 4.0],
+            "Comment" => ["平均", "月"]
+        )
+        .unwrap();
+        assert_eq!(en_df, expected);
+
+        let months = df!("月" => ["12月", "年間"], "2025年" => [1, 2]).unwrap();
+        let en_months = localize_frame(&months, Locale::En).unwrap();
+        assert_eq!(
+            en_months,
+            df!("Month" => ["Dec", "Annual"], "2025" => [1, 2]).unwrap()
+        );
+
+        // 文字列でない列の値はそのまま
+        let years = df!("年" => [2024, 2025], "平均" => [3.0, 4.0]).unwrap();
+        assert_eq!(
+            localize_frame(&years, Locale::En).unwrap(),
+            df!("Year" => [2024, 2025], "Mean" => [3.0, 4.0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_localize_records() {
+        let df = df!(
+            "日付" => ["2025-01-27"],
+            "体調" => [3],
+            "コメント" => ["月"],
+            "区分" => ["平日"],
+            "曜日" => ["月"]
+        )
+        .unwrap();
+        let expected = df!(
+            "Date" => ["2025-01-27"],
+            "Condition" => [3],
+            "Comment" => ["月"],
+            "区分" => ["平日"],
+            "Weekday" => ["Mon"]
+        )
+        .unwrap();
+        assert_eq!(localize_records(&df, Locale::En).unwrap(), expected);
+        assert_eq!(localize_records(&df, Locale::Ja).unwrap(), df);
+    }
+}
@@ -0,0 +1,422 @@
+use chrono::NaiveDate;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::schema::{COMMENT_COLUMN, DATE_COLUMN, PERSON_COLUMN};
+
+/// コメントを連結する際の区切り
+const COMMENT_SEPARATOR: &str = " / ";
+
+/// 同じ日付のデータがCSVとエクセルの両方にある場合の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeStrategy {
+    /// エクセル(手で修正した値)を優先する
+    PreferExcel,
+    /// CSVを優先する
+    #[default]
+    PreferCsv,
+    /// CSVを優先するが、CSVが空欄ならエクセルの値を残す
+    PreferNonNull,
+    /// コメントは両方を連結し、それ以外は空欄でない値を優先する
+    ConcatComments,
+}
+
+/// 同じ日付で値が異なっていた項目
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub date: NaiveDate,
+    /// 複数人のデータの場合は誰のデータか
+    pub person: Option<String>,
+    pub column: String,
+    /// 結合前の値 (エクセル、または先に読み込んだCSV)
+    pub excel: Option<String>,
+    pub csv: Option<String>,
+    /// 結合後に採用された値
+    pub resolved: Option<String>,
+}
+
+/// 結合したデータと、その際に見つかった競合
+pub struct MergeOutcome {
+    pub ldf: LazyFrame,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 同じ日付のデータはCSVを優先して結合する
+///
+/// 片方にしか無い列(エクセルに利用者が追加した列など)は、値のある方を残す。
+pub fn merge_condition_data(
+    csv_df: &DataFrame,
+    condition_df: &DataFrame,
+) -> PolarsResult<LazyFrame> {
+    Ok(merge_condition_data_with(csv_df, condition_df, MergeStrategy::default())?.ldf)
+}
+
+/// 指定した方針でデータを結合し、値が異なっていた日付を報告する
+///
+/// 人の列がある場合は日付と人の組み合わせ毎に結合する。
+/// 片方にしか人の列が無い場合、もう片方は人が空欄のデータとして扱う。
+pub fn merge_condition_data_with(
+    csv_df: &DataFrame,
+    condition_df: &DataFrame,
+    strategy: MergeStrategy,
+) -> PolarsResult<MergeOutcome> {
+    let has_person = [csv_df, condition_df]
+        .iter()
+        .any(|df| df.get_column_index(PERSON_COLUMN).is_some());
+    let csv_df = &with_person_column(csv_df, has_person)?;
+    let condition_df = &with_person_column(condition_df, has_person)?;
+    let keys: &[&str] = if has_person {
+        &[DATE_COLUMN, PERSON_COLUMN]
+    } else {
+        &[DATE_COLUMN]
+    };
+
+    let mut names: Vec<&PlSmallStr> = vec![];
+    let mut shared_names: Vec<&PlSmallStr> = vec![];
+    let mut agg_exprs = vec![];
+    for name in condition_df
+        .get_column_names()
+        .into_iter()
+        .chain(csv_df.get_column_names())
+    {
+        if keys.contains(&name.as_str()) || names.contains(&name) {
+            continue;
+        }
+        names.push(name);
+        // 結合元の行が先、CSVの行が後に並ぶ
+        let expr = if condition_df.get_column_index(name).is_none()
+            || csv_df.get_column_index(name).is_none()
+        {
+            col(name.clone()).drop_nulls().last()
+        } else {
+            shared_names.push(name);
+            match strategy {
+                MergeStrategy::PreferExcel => col(name.clone()).first(),
+                MergeStrategy::PreferCsv => col(name.clone()).last(),
+                MergeStrategy::PreferNonNull => col(name.clone()).drop_nulls().last(),
+                // 連結済みのコメントは区切りで分けてから重複を除き、何度結合しても同じにする
+                MergeStrategy::ConcatComments if name == COMMENT_COLUMN => col(name.clone())
+                    .drop_nulls()
+                    .str()
+                    .split(lit(COMMENT_SEPARATOR))
+                    .explode()
+                    .unique_stable()
+                    .str()
+                    .join(COMMENT_SEPARATOR, true),
+                MergeStrategy::ConcatComments => col(name.clone()).drop_nulls().last(),
+            }
+        };
+        agg_exprs.push(expr);
+    }
+
+    let mut ldf = concat_lf_diagonal(
+        [condition_df.clone().lazy(), csv_df.clone().lazy()],
+        UnionArgs::default(),
+    )?
+    .group_by_stable(keys.iter().map(|&key| col(key)).collect::<Vec<_>>())
+    .agg(agg_exprs);
+    if strategy == MergeStrategy::ConcatComments {
+        // コメントが1つも無い日は空欄に戻す
+        ldf = ldf.with_column(
+            when(col(COMMENT_COLUMN).eq(lit("")))
+                .then(lit(NULL).cast(DataType::String))
+                .otherwise(col(COMMENT_COLUMN))
+                .alias(COMMENT_COLUMN),
+        );
+    }
+    let ldf = ldf.sort(
+        keys.to_vec(),
+        SortMultipleOptions::new()
+            .with_order_descending(false)
+            .with_nulls_last(true)
+            .with_maintain_order(true),
+    );
+
+    let mut conflicts = find_conflicts(csv_df, condition_df, keys, &shared_names, &ldf)?;
+    if strategy == MergeStrategy::ConcatComments {
+        // CSVのコメントが既に連結されていて結合元のコメントが変わらない場合は報告しない
+        conflicts.retain(|conflict| {
+            conflict.column != COMMENT_COLUMN || conflict.excel != conflict.resolved
+        });
+    }
+    Ok(MergeOutcome { ldf, conflicts })
+}
+
+/// 人の列の無いデータを複数人のデータに結合する前に、誰のデータかを埋める
+///
+/// `person`が既にいる人の名前と一致するか1人の名前にだけ含まれればその人とし、
+/// それ以外は新しい人とする。人を選んでいなければ誰のデータか分からないためエラーにする。
+pub(crate) fn assign_person(
+    mut df: DataFrame,
+    persons: &[String],
+    person: Option<&str>,
+) -> ConditionResult<DataFrame> {
+    if persons.is_empty() || df.get_column_index(PERSON_COLUMN).is_some() {
+        return Ok(df);
+    }
+    let Some(person) = person.map(str::trim).filter(|person| !person.is_empty()) else {
+        return Err(ConditionError::PersonRequired {
+            persons: persons.to_vec(),
+        });
+    };
+    let matched: Vec<&String> = persons
+        .iter()
+        .filter(|name| name.contains(person))
+        .collect();
+    let name = match persons.iter().find(|name| *name == person) {
+        Some(name) => name.as_str(),
+        None if matched.len() == 1 => matched[0].as_str(),
+        None => person,
+    };
+    df.with_column(Column::new(PERSON_COLUMN.into(), vec![name; df.height()]))?;
+    Ok(df)
+}
+
+/// データにいる人の名前 (空欄の人は含まない)
+pub(crate) fn named_persons(df: &DataFrame) -> PolarsResult<Vec<String>> {
+    let Ok(persons) = df.column(PERSON_COLUMN) else {
+        return Ok(vec![]);
+    };
+    let persons = persons.cast(&DataType::String)?;
+    let mut names: Vec<String> = persons
+        .str()?
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// 人の列が無ければ空欄の人の列を追加する
+fn with_person_column(df: &DataFrame, has_person: bool) -> PolarsResult<DataFrame> {
+    let mut df = df.clone();
+    if !has_person {
+        return Ok(df);
+    }
+    let person = match df.column(PERSON_COLUMN) {
+        Ok(person) => person.cast(&DataType::String)?,
+        Err(_) => Column::full_null(PERSON_COLUMN.into(), df.height(), &DataType::String),
+    };
+    df.with_column(person)?;
+    Ok(df)
+}
+
+/// 両方にある日付で値が異なる項目を探す
+fn find_conflicts(
+    csv_df: &DataFrame,
+    condition_df: &DataFrame,
+    keys: &[&str],
+    shared_names: &[&PlSmallStr],
+    merged_ldf: &LazyFrame,
+) -> PolarsResult<Vec<MergeConflict>> {
+    let key_exprs: Vec<Expr> = keys.iter().map(|&key| col(key)).collect();
+    // 人が空欄のデータ同士も同じ人として比較する
+    let mut join_args = JoinArgs::new(JoinType::Inner).with_suffix(Some("_csv".into()));
+    join_args.join_nulls = true;
+    let joined_ldf =
+        condition_df
+            .clone()
+            .lazy()
+            .join(csv_df.clone().lazy(), &key_exprs, &key_exprs, join_args);
+
+    let mut conflicts = vec![];
+    for &name in shared_names {
+        let csv_name = format!("{name}_csv");
+        let mut resolved_exprs = key_exprs.clone();
+        resolved_exprs.push(col(name.clone()).alias("resolved"));
+        let resolved_ldf = merged_ldf.clone().select(resolved_exprs);
+        let mut left_join_args = JoinArgs::new(JoinType::Left);
+        left_join_args.join_nulls = true;
+        let mut select_exprs = key_exprs.clone();
+        select_exprs.extend([
+            col(name.clone()).cast(DataType::String).alias("excel"),
+            col(&csv_name).cast(DataType::String).alias("csv"),
+            col("resolved").cast(DataType::String),
+        ]);
+        let conflict_df = joined_ldf
+            .clone()
+            .filter(col(name.clone()).neq_missing(col(&csv_name)))
+            .join(resolved_ldf, &key_exprs, &key_exprs, left_join_args)
+            .select(select_exprs)
+            .collect()?;
+
+        let dates = conflict_df.column(DATE_COLUMN)?.date()?;
+        let persons = match conflict_df.column(PERSON_COLUMN) {
+            Ok(persons) => Some(persons.str()?),
+            Err(_) => None,
+        };
+        let excel = conflict_df.column("excel")?.str()?;
+        let csv = conflict_df.column("csv")?.str()?;
+        let resolved = conflict_df.column("resolved")?.str()?;
+        for (i, date) in dates.as_date_iter().enumerate() {
+            let Some(date) = date else {
+                continue;
+            };
+            conflicts.push(MergeConflict {
+                date,
+                person: persons.and_then(|p| p.get(i)).map(String::from),
+                column: name.to_string(),
+                excel: excel.get(i).map(String::from),
+                csv: csv.get(i).map(String::from),
+                resolved: resolved.get(i).map(String::from),
+            });
+        }
+    }
+    conflicts.sort_by(|a, b| (a.date, &a.person).cmp(&(b.date, &b.person)));
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    fn excel_df() -> DataFrame {
+        df!(
+            "日付" => [date(25), date(26), date(27)],
+            "体調" => [Some(2i32), Some(3i32), Some(4i32)],
+            "コメント" => [None, Some("手で修正"), Some("同じ")]
+        )
+        .unwrap()
+    }
+
+    fn csv_df() -> DataFrame {
+        df!(
+            "日付" => [date(26), date(27), date(28)],
+            "体調" => [None, Some(4i32), Some(5i32)],
+            "コメント" => [Some("CSV"), Some("同じ"), None]
+        )
+        .unwrap()
+    }
+
+    fn merged_column(outcome: &MergeOutcome, name: &str) -> Series {
+        outcome
+            .ldf
+            .clone()
+            .collect()
+            .unwrap()
+            .column(name)
+            .unwrap()
+            .as_materialized_series()
+            .clone()
+    }
+
+    #[test]
+    fn test_conflict_report() {
+        let outcome =
+            merge_condition_data_with(&csv_df(), &excel_df(), MergeStrategy::PreferCsv).unwrap();
+        assert_eq!(
+            outcome.conflicts,
+            vec![
+                MergeConflict {
+                    date: date(26),
+                    person: None,
+                    column: "体調".into(),
+                    excel: Some("3".into()),
+                    csv: None,
+                    resolved: None,
+                },
+                MergeConflict {
+                    date: date(26),
+                    person: None,
+                    column: "コメント".into(),
+                    excel: Some("手で修正".into()),
+                    csv: Some("CSV".into()),
+                    resolved: Some("CSV".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefer_excel() {
+        let outcome =
+            merge_condition_data_with(&csv_df(), &excel_df(), MergeStrategy::PreferExcel).unwrap();
+        let expected = Series::new("体調".into(), [Some(2i32), Some(3), Some(4), Some(5)]);
+        assert!(merged_column(&outcome, "体調").equals_missing(&expected));
+        assert_eq!(outcome.conflicts[1].resolved.as_deref(), Some("手で修正"));
+    }
+
+    #[test]
+    fn test_prefer_non_null() {
+        let outcome =
+            merge_condition_data_with(&csv_df(), &excel_df(), MergeStrategy::PreferNonNull)
+                .unwrap();
+        let expected = Series::new("体調".into(), [Some(2i32), Some(3), Some(4), Some(5)]);
+        assert!(merged_column(&outcome, "体調").equals_missing(&expected));
+    }
+
+    #[test]
+    fn test_concat_comments() {
+        let outcome =
+            merge_condition_data_with(&csv_df(), &excel_df(), MergeStrategy::ConcatComments)
+                .unwrap();
+        let expected = Series::new(
+            "コメント".into(),
+            [None, Some("手で修正 / CSV"), Some("同じ"), None],
+        );
+        assert!(merged_column(&outcome, "コメント").equals_missing(&expected));
+
+        // 結合済みのデータに同じCSVをもう一度結合しても変わらず、値の違いも報告しない
+        let merged_df = outcome.ldf.collect().unwrap();
+        let outcome =
+            merge_condition_data_with(&csv_df(), &merged_df, MergeStrategy::ConcatComments)
+                .unwrap();
+        assert!(merged_column(&outcome, "コメント").equals_missing(&expected));
+        assert!(outcome
+            .conflicts
+            .iter()
+            .all(|conflict| conflict.column != "コメント"));
+    }
+
+    #[test]
+    fn test_assign_person() {
+        let persons = vec!["太郎さん".to_string(), "愛さん".to_string()];
+        let err = assign_person(csv_df(), &persons, None).unwrap_err();
+        assert!(matches!(err, ConditionError::PersonRequired { .. }));
+
+        let df = assign_person(csv_df(), &persons, Some("愛")).unwrap();
+        assert_eq!(named_persons(&df).unwrap(), ["愛さん"]);
+        let df = assign_person(csv_df(), &persons, Some("蓮")).unwrap();
+        assert_eq!(named_persons(&df).unwrap(), ["蓮"]);
+        // 1人のデータにはそのまま結合する
+        let df = assign_person(csv_df(), &[], None).unwrap();
+        assert!(df.get_column_index("人").is_none());
+    }
+
+    #[test]
+    fn test_merge_by_person() {
+        let excel_df = df!(
+            "日付" => [date(26), date(26)],
+            "人" => ["愛さん", "太郎さん"],
+            "体調" => [2i32, 3]
+        )
+        .unwrap();
+        let csv_df = df!(
+            "日付" => [date(26), date(27)],
+            "人" => ["太郎さん", "太郎さん"],
+            "体調" => [4i32, 5]
+        )
+        .unwrap();
+        let outcome =
+            merge_condition_data_with(&csv_df, &excel_df, MergeStrategy::PreferCsv).unwrap();
+        let df = outcome.ldf.collect().unwrap();
+        let expected_df = df!(
+            "日付" => [date(26), date(26), date(27)],
+            "人" => ["太郎さん", "愛さん", "太郎さん"],
+            "体調" => [4i32, 2, 5]
+        )
+        .unwrap();
+        assert!(df.equals_missing(&expected_df));
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].person.as_deref(), Some("太郎さん"));
+    }
+}
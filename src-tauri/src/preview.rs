@@ -0,0 +1,230 @@
+use chrono::NaiveDate;
+use polars::prelude::*;
+use serde::Serialize;
+
+use crate::error::ConditionResult;
+use crate::merge::MergeConflict;
+use crate::scale::ScoreScale;
+use crate::schema::{COMMENT_COLUMN, CONDITION_COLUMN, DATE_COLUMN, PERSON_COLUMN};
+use crate::validate::Diagnostic;
+use crate::{
+    extract_yearly_frame_vec, person_frames, person_names, prepare_agg_frame, prepare_yearly_frame,
+    ConditionData, HolidayCalendar,
+};
+
+/// 保存前に確認するための結合結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataPreview {
+    pub rows: Vec<PreviewRow>,
+    /// 年毎(複数人のデータでは年と人毎)の集計表
+    pub yearly: Vec<YearlyAggregate>,
+    pub summary: PreviewSummary,
+    pub conflicts: Vec<MergeConflict>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// 結合後のdataシートの1行
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewRow {
+    pub date: Option<NaiveDate>,
+    pub person: Option<String>,
+    pub condition: Option<i32>,
+    pub comment: Option<String>,
+}
+
+/// 年毎のシートの集計表と同じ内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearlyAggregate {
+    pub year: i32,
+    pub person: Option<String>,
+    pub counts: Vec<ConditionCount>,
+}
+
+/// 集計表の1行 (ある体調だった日数)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionCount {
+    /// 調子の矢印
+    pub trend: String,
+    pub condition: i32,
+    pub annual: u32,
+    /// 1月〜12月の日数
+    pub monthly: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewSummary {
+    pub row_count: usize,
+    /// 体調が記録されている行数
+    pub recorded_days: usize,
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+    pub mean_condition: Option<f64>,
+    pub persons: Vec<String>,
+}
+
+/// 結合したデータから、保存せずに確認できる行・集計表・概要を作る
+pub fn preview_condition_data(
+    data: ConditionData,
+    scale: &ScoreScale,
+) -> ConditionResult<DataPreview> {
+    let df = data.ldf.clone().collect()?;
+    let dates = df.column(DATE_COLUMN)?.date()?;
+    let conditions = df.column(CONDITION_COLUMN)?.cast(&DataType::Int32)?;
+    let conditions = conditions.i32()?;
+    let comments = df.column(COMMENT_COLUMN)?.cast(&DataType::String)?;
+    let comments = comments.str()?;
+    let persons = match df.column(PERSON_COLUMN) {
+        Ok(persons) => Some(persons.cast(&DataType::String)?),
+        Err(_) => None,
+    };
+    let persons = persons.as_ref().map(|p| p.str()).transpose()?;
+
+    let rows = dates
+        .as_date_iter()
+        .enumerate()
+        .map(|(i, date)| PreviewRow {
+            date,
+            person: persons.and_then(|p| p.get(i)).map(String::from),
+            condition: conditions.get(i),
+            comment: comments.get(i).map(String::from),
+        })
+        .collect();
+
+    let summary = PreviewSummary {
+        row_count: df.height(),
+        recorded_days: df.height() - conditions.null_count(),
+        first_date: dates.as_date_iter().flatten().min(),
+        last_date: dates.as_date_iter().flatten().max(),
+        mean_condition: conditions.mean(),
+        persons: person_names(&data.ldf)?.unwrap_or_default(),
+    };
+
+    let groups = match person_frames(&data.ldf)? {
+        Some(frames) => frames
+            .into_iter()
+            .map(|(person, ldf)| (Some(person), ldf))
+            .collect(),
+        None => vec![(None, data.ldf.clone())],
+    };
+    let mut yearly = vec![];
+    for (person, ldf) in groups {
+        for yearly_data in extract_yearly_frame_vec(&ldf)? {
+            // 集計表は休日の影響を受けないため祝日は計算しない
+            let yearly_ldf = prepare_yearly_frame(
+                &yearly_data.ldf,
+                yearly_data.year,
+                &HolidayCalendar::new(),
+                scale,
+            )?;
+            let agg_df = prepare_agg_frame(&yearly_ldf, scale)?.collect()?;
+            yearly.push(YearlyAggregate {
+                year: yearly_data.year,
+                person: person.clone(),
+                counts: condition_counts(&agg_df)?,
+            });
+        }
+    }
+    yearly.sort_by(|a, b| (&a.person, a.year).cmp(&(&b.person, b.year)));
+
+    Ok(DataPreview {
+        rows,
+        yearly,
+        summary,
+        conflicts: data.conflicts,
+        diagnostics: data.diagnostics,
+    })
+}
+
+fn condition_counts(agg_df: &DataFrame) -> PolarsResult<Vec<ConditionCount>> {
+    let count_column = |name: &str| -> PolarsResult<Option<Column>> {
+        match agg_df.column(name) {
+            Ok(column) => Ok(Some(column.cast(&DataType::UInt32)?)),
+            Err(_) => Ok(None),
+        }
+    };
+    let trends = agg_df.column("調子")?.str()?;
+    let conditions = agg_df.column(CONDITION_COLUMN)?.cast(&DataType::Int32)?;
+    let annual = count_column("年間")?;
+    let monthly = (1..=12)
+        .map(|month| count_column(&format!("{month}月")))
+        .collect::<PolarsResult<Vec<_>>>()?;
+
+    let count = |column: &Option<Column>, i: usize| -> PolarsResult<u32> {
+        Ok(match column {
+            Some(column) => column.u32()?.get(i).unwrap_or_default(),
+            None => 0,
+        })
+    };
+    (0..agg_df.height())
+        .map(|i| {
+            Ok(ConditionCount {
+                trend: trends.get(i).unwrap_or_default().to_string(),
+                condition: conditions.i32()?.get(i).unwrap_or_default(),
+                annual: count(&annual, i)?,
+                monthly: monthly
+                    .iter()
+                    .map(|column| count(column, i))
+                    .collect::<PolarsResult<_>>()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_preview_condition_data() {
+        let df = df!(
+            "日付" => [date(1, 27), date(1, 28), date(2, 1)],
+            "体調" => [Some(3i32), None, Some(5)],
+            "コメント" => [Some("good"), None, None]
+        )
+        .unwrap();
+        let preview = preview_condition_data(
+            ConditionData {
+                ldf: df.lazy(),
+                conflicts: vec![],
+                diagnostics: vec![],
+                import: None,
+            },
+            &ScoreScale::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            preview.rows[0],
+            PreviewRow {
+                date: Some(date(1, 27)),
+                person: None,
+                condition: Some(3),
+                comment: Some("good".into()),
+            }
+        );
+        assert_eq!(
+            preview.summary,
+            PreviewSummary {
+                row_count: 3,
+                recorded_days: 2,
+                first_date: Some(date(1, 27)),
+                last_date: Some(date(2, 1)),
+                mean_condition: Some(4.0),
+                persons: vec![],
+            }
+        );
+        assert_eq!(preview.yearly.len(), 1);
+        let best = &preview.yearly[0].counts[0];
+        assert_eq!((best.condition, best.annual), (5, 1));
+        assert_eq!(best.monthly[..3], [0, 1, 0]);
+    }
+}
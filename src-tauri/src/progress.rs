@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::error::{ConditionError, ConditionResult};
+
+/// レポート作成の中断を指示するためのトークン
+///
+/// 複製したトークンは同じ状態を共有するため、別のスレッドから中断できる。
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// レポート作成の進み具合
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+pub enum ReportProgress {
+    /// CSVまたはエクセルファイルの読み込み (indexは1から)
+    Parsing {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+    },
+    /// 読み込んだCSVの結合 (indexは1から)
+    Merging { index: usize, total: usize },
+    /// 年毎のシートの作成 (indexは1から)
+    Sheet {
+        year: i32,
+        person: Option<String>,
+        index: usize,
+        total: usize,
+    },
+    /// ファイルの保存
+    Saving,
+}
+
+type ProgressCallback = dyn Fn(&ReportProgress) + Send + Sync;
+
+/// 進み具合の通知先と中断の指示をまとめたもの
+///
+/// 各段階を始める前に中断の指示を確認し、指示されていれば`ConditionError::Cancelled`を返す。
+#[derive(Clone, Default)]
+pub struct Progress {
+    callback: Option<Arc<ProgressCallback>>,
+    cancel_token: CancelToken,
+}
+
+impl Progress {
+    pub fn new(callback: impl Fn(&ReportProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            cancel_token: CancelToken::new(),
+        }
+    }
+
+    pub fn with_cancel_token(mut self, cancel_token: CancelToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    pub(crate) fn report(&self, progress: ReportProgress) -> ConditionResult<()> {
+        if self.cancel_token.is_cancelled() {
+            return Err(ConditionError::Cancelled);
+        }
+        if let Some(callback) = &self.callback {
+            callback(&progress);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_report_until_cancelled() {
+        let reported = Arc::new(Mutex::new(vec![]));
+        let cancel_token = CancelToken::new();
+        let progress = Progress::new({
+            let reported = reported.clone();
+            move |p| reported.lock().unwrap().push(p.clone())
+        })
+        .with_cancel_token(cancel_token.clone());
+
+        progress.report(ReportProgress::Saving).unwrap();
+        cancel_token.cancel();
+        let err = progress.report(ReportProgress::Saving).unwrap_err();
+
+        assert!(matches!(err, ConditionError::Cancelled));
+        assert_eq!(*reported.lock().unwrap(), vec![ReportProgress::Saving]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// 体調の値の範囲と、集計表・グラフで使う値毎の表示
+///
+/// 既定は0〜5の体調で、Rhythm Careの調子の矢印を表示する。
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScoreScale {
+    pub min: i32,
+    pub max: i32,
+    /// 低調連続日数で体調が低いとみなす値 (この値以下)
+    pub low_threshold: i32,
+    /// グラフの縦軸の最小値 (指定が無いか範囲外なら`min`)
+    ///
+    /// 既定は1で、平日の土日判定の値(`min`)はグラフの背景に表示しない。
+    pub axis_min: Option<i32>,
+    /// 値毎の表示 (範囲内の値を1つずつ、集計表に並べる順に指定する)
+    pub levels: Vec<ScoreLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreLevel {
+    pub value: i32,
+    pub label: String,
+    /// 分布のグラフで使う色 (`#RRGGBB`形式、無ければエクセルの既定の色)
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl Default for ScoreScale {
+    fn default() -> Self {
+        let level = |value, label: &str, color: &str| ScoreLevel {
+            value,
+            label: label.into(),
+            color: Some(color.into()),
+        };
+        Self {
+            min: 0,
+            max: 5,
+            low_threshold: 2,
+            axis_min: Some(1),
+            levels: vec![
+                level(5, "↑", "#00B050"),
+                level(4, "↗", "#92D050"),
+                level(3, "→", "#FFC000"),
+                level(2, "↘", "#FF9900"),
+                level(1, "↓", "#FF0000"),
+                level(0, "⇓", "#C00000"),
+            ],
+        }
+    }
+}
+
+impl ScoreScale {
+    pub fn contains(&self, value: i32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+
+    /// グラフの縦軸の最小値
+    pub fn axis_min(&self) -> i32 {
+        self.axis_min
+            .filter(|&axis_min| self.min <= axis_min && axis_min < self.max)
+            .unwrap_or(self.min)
+    }
+
+    /// 範囲内の値が1つずつ表示されているかを確認する
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min >= self.max {
+            return Err(format!(
+                "score scale min {} must be less than max {}",
+                self.min, self.max
+            ));
+        }
+        if !self.contains(self.low_threshold) {
+            return Err(format!(
+                "low threshold {} is outside the score scale",
+                self.low_threshold
+            ));
+        }
+        let mut seen = HashSet::new();
+        for level in &self.levels {
+            if !self.contains(level.value) || !seen.insert(level.value) {
+                return Err(format!(
+                    "score level {} is outside the scale or listed twice",
+                    level.value
+                ));
+            }
+            if level.label.is_empty() {
+                return Err(format!("score level {} has no label", level.value));
+            }
+        }
+        if seen.len() != (self.min..=self.max).count() {
+            return Err("every score in the scale needs a level".into());
+        }
+        Ok(())
+    }
+}
+
+impl ScoreLevel {
+    /// 体調毎の日数を並べる表の列名 (表示に値が含まれなければ値を付ける)
+    pub fn column_name(&self) -> String {
+        if self.label == self.value.to_string() {
+            self.label.clone()
+        } else {
+            format!("{}{}", self.label, self.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_pain_scale() {
+        let mut scale = ScoreScale {
+            min: 1,
+            max: 10,
+            low_threshold: 3,
+            axis_min: None,
+            levels: (1..=10)
+                .rev()
+                .map(|value| ScoreLevel {
+                    value,
+                    label: value.to_string(),
+                    color: None,
+                })
+                .collect(),
+        };
+        assert_eq!(scale.validate(), Ok(()));
+        assert_eq!(scale.levels[0].column_name(), "10");
+        assert_eq!(ScoreScale::default().levels[0].column_name(), "↑5");
+        assert_eq!(scale.axis_min(), 1);
+        assert_eq!(ScoreScale::default().axis_min(), 1);
+        scale.axis_min = Some(10);
+        assert_eq!(scale.axis_min(), 1);
+
+        scale.levels.pop();
+        assert!(scale.validate().is_err());
+    }
+}
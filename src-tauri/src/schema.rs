@@ -0,0 +1,213 @@
+use chrono::NaiveDate;
+use polars::prelude::*;
+
+use crate::locale::Locale;
+
+/// 体調データと年毎のシートの列
+///
+/// データフレームでは常に日本語の列名を使い、エクセルに書き出す時だけ見出しを選んだ言語にする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionColumn {
+    Date,
+    /// 複数人のデータで誰のデータか
+    Person,
+    Condition,
+    Comment,
+    Weekday,
+    /// 土日祝はグラフの背景に色を付けるため体調の最大値、平日は最小値
+    DayOff,
+    Holiday,
+    WeeklyMean,
+    MonthlyMean,
+    DailyChange,
+    LowStreak,
+}
+
+impl ConditionColumn {
+    pub const ALL: [Self; 11] = [
+        Self::Date,
+        Self::Person,
+        Self::Condition,
+        Self::Comment,
+        Self::Weekday,
+        Self::DayOff,
+        Self::Holiday,
+        Self::WeeklyMean,
+        Self::MonthlyMean,
+        Self::DailyChange,
+        Self::LowStreak,
+    ];
+
+    /// データフレームでの列名 (保存先やdataシートの見出しとしても使う)
+    pub const fn name(self) -> &'static str {
+        self.label(Locale::Ja)
+    }
+
+    pub fn dtype(self) -> DataType {
+        match self {
+            Self::Date => DataType::Date,
+            Self::Person | Self::Comment | Self::Weekday | Self::Holiday => DataType::String,
+            Self::Condition | Self::DayOff | Self::DailyChange => DataType::Int32,
+            Self::WeeklyMean | Self::MonthlyMean => DataType::Float64,
+            Self::LowStreak => DataType::UInt32,
+        }
+    }
+
+    /// エクセルに書き出す見出し
+    pub const fn label(self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::Ja => match self {
+                Self::Date => "日付",
+                Self::Person => "人",
+                Self::Condition => "体調",
+                Self::Comment => "コメント",
+                Self::Weekday => "曜日",
+                Self::DayOff => "土日判定",
+                Self::Holiday => "祝日",
+                Self::WeeklyMean => "7日平均",
+                Self::MonthlyMean => "30日平均",
+                Self::DailyChange => "前日差",
+                Self::LowStreak => "低調連続日数",
+            },
+            Locale::En => match self {
+                Self::Date => "Date",
+                Self::Person => "Person",
+                Self::Condition => "Condition",
+                Self::Comment => "Comment",
+                Self::Weekday => "Weekday",
+                Self::DayOff => "Day off",
+                Self::Holiday => "Holiday",
+                Self::WeeklyMean => "7-day mean",
+                Self::MonthlyMean => "30-day mean",
+                Self::DailyChange => "Change",
+                Self::LowStreak => "Low streak",
+            },
+        }
+    }
+
+    /// 見出しから列を判定する (どの言語で書き出した見出しでもよい)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|column| {
+            [Locale::Ja, Locale::En]
+                .into_iter()
+                .any(|locale| column.label(locale) == label)
+        })
+    }
+}
+
+pub(crate) const DATE_COLUMN: &str = ConditionColumn::Date.name();
+pub(crate) const PERSON_COLUMN: &str = ConditionColumn::Person.name();
+pub(crate) const CONDITION_COLUMN: &str = ConditionColumn::Condition.name();
+pub(crate) const COMMENT_COLUMN: &str = ConditionColumn::Comment.name();
+pub(crate) const WEEKDAY_COLUMN: &str = ConditionColumn::Weekday.name();
+pub(crate) const DAY_OFF_COLUMN: &str = ConditionColumn::DayOff.name();
+pub(crate) const HOLIDAY_COLUMN: &str = ConditionColumn::Holiday.name();
+/// 年毎のシートの7日移動平均の列 (月毎のグラフにも表示する)
+pub(crate) const WEEKLY_MEAN_COLUMN: &str = ConditionColumn::WeeklyMean.name();
+pub(crate) const MONTHLY_MEAN_COLUMN: &str = ConditionColumn::MonthlyMean.name();
+pub(crate) const DAILY_CHANGE_COLUMN: &str = ConditionColumn::DailyChange.name();
+pub(crate) const LOW_STREAK_COLUMN: &str = ConditionColumn::LowStreak.name();
+
+/// 読み込んだ1日分(複数人のデータでは1日1人分)の体調の記録
+///
+/// エクセルで日付を読み取れなかった行は検証結果に残すため、日付が空のまま読み込む。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConditionRecord {
+    pub date: Option<NaiveDate>,
+    pub person: Option<String>,
+    pub condition: Option<i32>,
+    pub comment: Option<String>,
+}
+
+impl ConditionRecord {
+    /// 日付・(人)・体調・コメントの列のデータに変換する
+    pub fn to_frame(records: &[Self], with_person: bool) -> PolarsResult<DataFrame> {
+        let mut columns = vec![
+            Column::new(
+                DATE_COLUMN.into(),
+                records.iter().map(|r| r.date).collect::<Vec<_>>(),
+            ),
+            Column::new(
+                CONDITION_COLUMN.into(),
+                records.iter().map(|r| r.condition).collect::<Vec<_>>(),
+            ),
+            Column::new(
+                COMMENT_COLUMN.into(),
+                records
+                    .iter()
+                    .map(|r| r.comment.clone())
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        if with_person {
+            let persons: Vec<Option<String>> = records.iter().map(|r| r.person.clone()).collect();
+            columns.insert(1, Column::new(PERSON_COLUMN.into(), persons));
+        }
+        // 全て空欄の列も型を揃える
+        let columns = columns
+            .into_iter()
+            .map(|column| {
+                let dtype = ConditionColumn::from_label(column.name().as_str())
+                    .map(ConditionColumn::dtype)
+                    .unwrap_or(DataType::String);
+                column.cast(&dtype)
+            })
+            .collect::<PolarsResult<Vec<_>>>()?;
+        DataFrame::new(columns)
+    }
+}
+
+/// 体調データの見出しをこの言語にする (値や利用者が追加した列の見出しは変えない)
+pub(crate) fn localize_headers(df: &DataFrame, locale: Locale) -> PolarsResult<DataFrame> {
+    let mut df = df.clone();
+    for column in ConditionColumn::ALL {
+        if df.get_column_index(column.name()).is_some() {
+            df.rename(column.name(), column.label(locale).into())?;
+        }
+    }
+    Ok(df)
+}
+
+/// 見出しをデータフレームでの列名にする (英語で書き出したdataシートを読み込むため)
+pub(crate) fn column_name(label: &str) -> &str {
+    ConditionColumn::from_label(label).map_or(label, |column| column.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_headers() {
+        let records = [ConditionRecord {
+            date: NaiveDate::from_ymd_opt(2025, 1, 27),
+            person: Some("未設定".into()),
+            condition: None,
+            comment: Some("good".into()),
+        }];
+        let mut df = ConditionRecord::to_frame(&records, true).unwrap();
+        assert_eq!(
+            df.column(CONDITION_COLUMN).unwrap().dtype(),
+            &DataType::Int32
+        );
+        df.with_column(Series::new("睡眠".into(), [7.5])).unwrap();
+
+        let en_df = localize_headers(&df, Locale::En).unwrap();
+        let headers: Vec<&str> = en_df
+            .get_column_names()
+            .into_iter()
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(headers, ["Date", "Person", "Condition", "Comment", "睡眠"]);
+        // 値は利用者の入力したものなのでそのまま
+        assert_eq!(
+            en_df.column("Person").unwrap(),
+            &Column::new("Person".into(), ["未設定"])
+        );
+        assert_eq!(localize_headers(&df, Locale::Ja).unwrap(), df);
+
+        for header in headers {
+            assert!(df.get_column_index(column_name(header)).is_some());
+        }
+    }
+}
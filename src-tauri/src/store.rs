@@ -0,0 +1,1199 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+use polars::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::merge::{merge_condition_data_with, MergeStrategy};
+use crate::scale::ScoreScale;
+use crate::schema::{
+    ConditionRecord, COMMENT_COLUMN, CONDITION_COLUMN, DATE_COLUMN, PERSON_COLUMN,
+};
+use crate::validate::validate_entry;
+use crate::ConditionData;
+
+/// アプリのデータディレクトリに作成する保存先のファイル名
+pub const STORE_FILE: &str = "conditions.sqlite3";
+
+/// 保存先のテーブルの変更 (適用済みの数を`user_version`に記録する)
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE entries (
+        date TEXT NOT NULL,
+        person TEXT NOT NULL DEFAULT '',
+        condition INTEGER,
+        comment TEXT,
+        extra TEXT NOT NULL DEFAULT '{}',
+        PRIMARY KEY (date, person)
+    );
+    CREATE TABLE columns (
+        position INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        dtype TEXT NOT NULL
+    );
+",
+    "
+    CREATE TABLE imports (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        imported_at TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        sources TEXT NOT NULL,
+        added INTEGER NOT NULL,
+        changed INTEGER NOT NULL,
+        rolled_back_at TEXT
+    );
+    CREATE TABLE import_changes (
+        import_id INTEGER NOT NULL REFERENCES imports (id),
+        date TEXT NOT NULL,
+        person TEXT NOT NULL,
+        before TEXT,
+        after TEXT,
+        PRIMARY KEY (import_id, date, person)
+    );
+",
+];
+
+/// 日付・人・体調・コメント以外で保存先に専用の列が無い列
+const CORE_COLUMNS: [&str; 4] = [DATE_COLUMN, PERSON_COLUMN, CONDITION_COLUMN, COMMENT_COLUMN];
+
+type StoreResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// 日付と人 (保存先の記録を特定する組み合わせ、人が空欄なら空文字列)
+type EntryKey = (NaiveDate, String);
+
+/// 読み込んだ列の値 (日付・人・体調・コメント・追加した列のJSON)
+type EntryRow = (NaiveDate, String, Option<i32>, Option<String>, String);
+
+const ENTRY_COLUMNS: &str = "date, person, condition, comment, extra";
+
+/// 保存されている1日分(複数人のデータでは1日1人分)の記録
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionEntry {
+    pub date: NaiveDate,
+    pub person: Option<String>,
+    pub condition: Option<i32>,
+    pub comment: Option<String>,
+    /// 睡眠時間や服薬など利用者が追加した列の値 (空欄の列は含まない)
+    pub extra: Map<String, Value>,
+}
+
+impl ConditionEntry {
+    fn key(&self) -> EntryKey {
+        (self.date, self.person.clone().unwrap_or_default())
+    }
+
+    fn from_row((date, person, condition, comment, extra): EntryRow) -> StoreResult<Self> {
+        Ok(Self {
+            date,
+            person: Some(person).filter(|person| !person.is_empty()),
+            condition,
+            comment,
+            extra: serde_json::from_str(&extra)?,
+        })
+    }
+}
+
+/// 手で追加・修正する記録 (利用者が追加した列は保存されている値を残す)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryInput {
+    pub date: NaiveDate,
+    pub person: Option<String>,
+    pub condition: Option<i32>,
+    pub comment: Option<String>,
+}
+
+impl EntryInput {
+    /// 検証して保存する記録にする (空欄の人やコメントは無いものとして扱う)
+    fn validate(self, scale: &ScoreScale) -> ConditionResult<ConditionEntry> {
+        let today = Local::now().date_naive();
+        validate_entry(self.date, self.condition, today, scale)?;
+        let non_empty = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(ConditionEntry {
+            date: self.date,
+            person: non_empty(self.person),
+            condition: self.condition,
+            comment: non_empty(self.comment),
+            extra: Map::new(),
+        })
+    }
+}
+
+/// 取り込んだファイルと、その内容のハッシュ (同じファイルを取り込み直したか確かめるため)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSource {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl ImportSource {
+    /// ファイルの内容のハッシュを求める
+    pub fn read(path: &Path) -> ConditionResult<Self> {
+        let bytes = fs::read(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ConditionError::FileNotFound { path: path.into() },
+            _ => ConditionError::store(path, e),
+        })?;
+        let sha256 = Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Ok(Self {
+            path: path.into(),
+            sha256,
+        })
+    }
+}
+
+/// 取り込みの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportKind {
+    /// CSVや前回のエクセルファイルの読み込み
+    Files,
+    /// 記録の手での追加・修正・削除
+    Manual,
+}
+
+impl ImportKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Files => "files",
+            Self::Manual => "manual",
+        }
+    }
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "manual" => Self::Manual,
+            _ => Self::Files,
+        }
+    }
+}
+
+/// 1回の取り込みの記録
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportBatch {
+    pub id: i64,
+    pub imported_at: DateTime<Local>,
+    pub kind: ImportKind,
+    pub sources: Vec<ImportSource>,
+    /// 新しく加わった記録の数
+    pub added: usize,
+    /// 値が変わった記録の数
+    pub changed: usize,
+    /// 取り消した日時
+    pub rolled_back_at: Option<DateTime<Local>>,
+}
+
+/// 取り込みを取り消した結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackSummary {
+    pub id: i64,
+    /// 取り込む前の値に戻した(取り込みで加わったものは削除した)記録の数
+    pub restored: usize,
+    /// 後の取り込みで値が変わっていたため、そのままにした記録 (今の値)
+    pub skipped: Vec<ConditionEntry>,
+}
+
+/// 利用者が追加した列の値の型 (読み込む際に同じ型の列に戻す)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Float,
+    Bool,
+    Date,
+    String,
+}
+
+impl ColumnType {
+    fn of(dtype: &DataType) -> Self {
+        match dtype {
+            DataType::Boolean => Self::Bool,
+            DataType::Date => Self::Date,
+            dtype if dtype.is_primitive_numeric() => Self::Float,
+            _ => Self::String,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Date => "date",
+            Self::String => "string",
+        }
+    }
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "float" => Self::Float,
+            "bool" => Self::Bool,
+            "date" => Self::Date,
+            _ => Self::String,
+        }
+    }
+}
+
+/// 取り込んだ全てのデータを保存するSQLiteのデータベース
+///
+/// 前回のエクセルファイルの代わりにこちらを正とし、CSVやエクセルは読み込む度にここへ結合する。
+pub struct ConditionStore {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl ConditionStore {
+    /// 保存先を開く (無ければ作成する)
+    pub fn open(path: impl AsRef<Path>) -> ConditionResult<Self> {
+        let path = path.as_ref();
+        let open = || -> StoreResult<Connection> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            Ok(Connection::open(path)?)
+        };
+        let conn = open().map_err(|e| ConditionError::store(path, e))?;
+        Self::with_connection(conn, path)
+    }
+
+    /// ファイルに保存しない保存先 (テストやCLIの一時的な利用向け)
+    pub fn open_in_memory() -> ConditionResult<Self> {
+        let path = Path::new(":memory:");
+        let conn = Connection::open_in_memory().map_err(|e| ConditionError::store(path, e))?;
+        Self::with_connection(conn, path)
+    }
+
+    fn with_connection(conn: Connection, path: &Path) -> ConditionResult<Self> {
+        let mut store = Self {
+            conn,
+            path: path.into(),
+        };
+        store.migrate().map_err(|e| store.error(e))?;
+        Ok(store)
+    }
+
+    fn error(&self, err: impl Display) -> ConditionError {
+        ConditionError::store(&self.path, err)
+    }
+
+    fn migrate(&mut self) -> StoreResult<()> {
+        let version: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let tx = self.conn.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 保存されている全てのデータを、読み込んだファイルと同じ列の形で返す
+    pub fn load(&self) -> ConditionResult<DataFrame> {
+        self.read_frame().map_err(|e| self.error(e))
+    }
+
+    /// 期間内(両端を含む)の記録を日付順に返す
+    pub fn entries(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> ConditionResult<Vec<ConditionEntry>> {
+        self.read_entries(from, to).map_err(|e| self.error(e))
+    }
+
+    /// 保存されている記録にいる人の名前 (空欄の人は含まない)
+    pub fn persons(&self) -> ConditionResult<Vec<String>> {
+        let read = || -> StoreResult<Vec<String>> {
+            let mut stmt = self.conn.prepare(
+                "SELECT DISTINCT person FROM entries WHERE person != '' ORDER BY person",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            Ok(rows.collect::<Result<_, _>>()?)
+        };
+        read().map_err(|e| self.error(e))
+    }
+
+    /// 読み込んだデータを保存されているデータに結合した結果を返す (保存はしない)
+    ///
+    /// 読み込んだデータは前回のデータに対するCSVと同じ扱いで、`strategy`に従って値を選ぶ。
+    /// ただし最後に手で修正した記録は`strategy`によらず保存されている値を残し、
+    /// 読み込んだ値と異なれば競合として報告する。
+    pub fn merge(
+        &self,
+        data: ConditionData,
+        strategy: MergeStrategy,
+    ) -> ConditionResult<ConditionData> {
+        let stored_df = self.load()?;
+        let imported_df = data.ldf.collect()?;
+        let edited = self.manual_keys().map_err(|e| self.error(e))?;
+        let mut conflicts = data.conflicts;
+        let ldf = if edited.is_empty() {
+            let merged = merge_condition_data_with(&imported_df, &stored_df, strategy)?;
+            conflicts.extend(merged.conflicts);
+            merged.ldf
+        } else {
+            let mask = frame_keys(&stored_df)?
+                .into_iter()
+                .map(|key| key.is_some_and(|key| edited.contains(&key)))
+                .collect::<BooleanChunked>();
+            let edited_df = stored_df.filter(&mask)?;
+            let other_df = stored_df.filter(&!&mask)?;
+            let merged = merge_condition_data_with(&imported_df, &other_df, strategy)?;
+            conflicts.extend(merged.conflicts);
+            // 手で修正した記録を、読み込んだ値より優先して結合し直す
+            let merged_df = merged.ldf.collect()?;
+            let merged =
+                merge_condition_data_with(&merged_df, &edited_df, MergeStrategy::PreferExcel)?;
+            conflicts.extend(merged.conflicts);
+            conflicts.sort_by(|a, b| (a.date, &a.person).cmp(&(b.date, &b.person)));
+            merged.ldf
+        };
+        Ok(ConditionData {
+            ldf,
+            conflicts,
+            diagnostics: data.diagnostics,
+            import: None,
+        })
+    }
+
+    /// 読み込んだデータを結合して保存し、保存されている全てのデータを返す
+    ///
+    /// 加わった記録と値が変わった記録は、取り消せるよう取り込み前の値と共に履歴に残す。
+    /// 変わった記録が無ければ履歴には残さない。
+    pub fn import(
+        &mut self,
+        data: ConditionData,
+        strategy: MergeStrategy,
+        sources: Vec<ImportSource>,
+    ) -> ConditionResult<ConditionData> {
+        let mut merged = self.merge(data, strategy)?;
+        let merged_df = merged.ldf.collect()?;
+        let batch = self.apply(&merged_df, sources).map_err(|e| self.error(e))?;
+        merged.ldf = merged_df.lazy();
+        merged.import = batch;
+        Ok(merged)
+    }
+
+    /// 記録を手で追加する (同じ日付と人の記録が既にあればエラー)
+    ///
+    /// 手での変更も、ファイルの取り込みと区別して履歴に残す (取り消せるように)。
+    pub fn add_entry(
+        &mut self,
+        input: EntryInput,
+        scale: &ScoreScale,
+    ) -> ConditionResult<ConditionEntry> {
+        let entry = input.validate(scale)?;
+        if self.entry(&entry.key())?.is_some() {
+            return Err(ConditionError::EntryExists {
+                date: entry.date,
+                person: entry.person,
+            });
+        }
+        self.edit(&entry.key(), None, Some(entry.clone()))?;
+        Ok(entry)
+    }
+
+    /// 保存されている記録の体調とコメントを修正する
+    pub fn update_entry(
+        &mut self,
+        input: EntryInput,
+        scale: &ScoreScale,
+    ) -> ConditionResult<ConditionEntry> {
+        let mut entry = input.validate(scale)?;
+        let Some(before) = self.entry(&entry.key())? else {
+            return Err(ConditionError::EntryNotFound {
+                date: entry.date,
+                person: entry.person,
+            });
+        };
+        entry.extra = before.extra.clone();
+        if entry != before {
+            self.edit(&entry.key(), Some(before), Some(entry.clone()))?;
+        }
+        Ok(entry)
+    }
+
+    /// 保存されている記録を削除する
+    pub fn delete_entry(&mut self, date: NaiveDate, person: Option<String>) -> ConditionResult<()> {
+        let key = (date, person.clone().unwrap_or_default());
+        let Some(before) = self.entry(&key)? else {
+            return Err(ConditionError::EntryNotFound { date, person });
+        };
+        self.edit(&key, Some(before), None)?;
+        Ok(())
+    }
+
+    fn entry(&self, key: &EntryKey) -> ConditionResult<Option<ConditionEntry>> {
+        read_entry(&self.conn, key).map_err(|e| self.error(e))
+    }
+
+    fn edit(
+        &mut self,
+        key: &EntryKey,
+        before: Option<ConditionEntry>,
+        after: Option<ConditionEntry>,
+    ) -> ConditionResult<()> {
+        let write = || -> StoreResult<()> {
+            let tx = self.conn.transaction()?;
+            let changes = vec![(key.clone(), before, after)];
+            write_changes(&tx, changes, vec![], ImportKind::Manual)?;
+            tx.commit()?;
+            Ok(())
+        };
+        write().map_err(|e| self.error(e))
+    }
+
+    /// これまでの取り込みを新しい順に返す
+    pub fn imports(&self) -> ConditionResult<Vec<ImportBatch>> {
+        self.read_imports(None).map_err(|e| self.error(e))
+    }
+
+    /// 取り込みを取り消し、その取り込みで加わった記録と変わった記録を元に戻す
+    ///
+    /// 後の取り込みで更に値が変わった記録は、後の取り込みを残すためそのままにする。
+    pub fn rollback(&mut self, id: i64) -> ConditionResult<RollbackSummary> {
+        let batch = self
+            .read_imports(Some(id))
+            .map_err(|e| self.error(e))?
+            .pop()
+            .ok_or_else(|| self.error(format!("import #{id} was not found")))?;
+        if batch.rolled_back_at.is_some() {
+            return Err(self.error(format!("import #{id} was already rolled back")));
+        }
+        self.revert(id).map_err(|e| self.error(e))
+    }
+
+    fn read_entries(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<ConditionEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM entries
+             WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+             ORDER BY date, person"
+        ))?;
+        let rows = stmt.query_map(params![from, to], entry_row)?;
+        let mut entries = vec![];
+        for row in rows {
+            entries.push(ConditionEntry::from_row(row?)?);
+        }
+        Ok(entries)
+    }
+
+    /// 取り込みの記録 (`id`を指定しなければ全て)
+    fn read_imports(&self, id: Option<i64>) -> StoreResult<Vec<ImportBatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, imported_at, kind, sources, added, changed, rolled_back_at FROM imports
+             WHERE ?1 IS NULL OR id = ?1
+             ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map(params![id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+            ))
+        })?;
+        let mut batches = vec![];
+        for row in rows {
+            let (id, imported_at, kind, sources, added, changed, rolled_back_at) = row?;
+            batches.push(ImportBatch {
+                id,
+                imported_at,
+                kind: ImportKind::parse(&kind),
+                sources: serde_json::from_str(&sources)?,
+                added,
+                changed,
+                rolled_back_at,
+            });
+        }
+        Ok(batches)
+    }
+
+    /// 取り消されていない最後の変更が手での修正である、保存されている記録
+    fn manual_keys(&self) -> StoreResult<HashSet<EntryKey>> {
+        // SQLiteでは集約しない列は max() の行の値になる
+        let mut stmt = self.conn.prepare(
+            "SELECT c.date, c.person, i.kind, c.after IS NOT NULL, max(i.id)
+             FROM import_changes c JOIN imports i ON i.id = c.import_id
+             WHERE i.rolled_back_at IS NULL
+             GROUP BY c.date, c.person",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                (row.get(0)?, row.get(1)?),
+                row.get::<_, String>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?;
+        let mut keys = HashSet::new();
+        for row in rows {
+            let (key, kind, exists) = row?;
+            if exists && ImportKind::parse(&kind) == ImportKind::Manual {
+                keys.insert(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    fn read_columns(&self) -> StoreResult<Vec<(String, ColumnType)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, dtype FROM columns ORDER BY position")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, ColumnType::parse(&row.get::<_, String>(1)?)))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn read_frame(&self) -> StoreResult<DataFrame> {
+        let entries = self.read_entries(None, None)?;
+        let records: Vec<ConditionRecord> = entries
+            .iter()
+            .map(|entry| ConditionRecord {
+                date: Some(entry.date),
+                person: entry.person.clone(),
+                condition: entry.condition,
+                comment: entry.comment.clone(),
+            })
+            .collect();
+        // 全て空欄なら人の列の無いデータとして扱う
+        let with_person = entries.iter().any(|entry| entry.person.is_some());
+        let mut df = ConditionRecord::to_frame(&records, with_person)?;
+        for (name, dtype) in self.read_columns()? {
+            let values = entries.iter().map(|entry| entry.extra.get(&name));
+            let series = match dtype {
+                ColumnType::Float => {
+                    let values: Vec<Option<f64>> = values.map(|v| v?.as_f64()).collect();
+                    Series::new(name.into(), values)
+                }
+                ColumnType::Bool => {
+                    let values: Vec<Option<bool>> = values.map(|v| v?.as_bool()).collect();
+                    Series::new(name.into(), values)
+                }
+                ColumnType::Date => {
+                    let values: Vec<Option<NaiveDate>> =
+                        values.map(|v| v?.as_str()?.parse().ok()).collect();
+                    Series::new(name.into(), values)
+                }
+                ColumnType::String => {
+                    let values: Vec<Option<&str>> = values.map(|v| v?.as_str()).collect();
+                    Series::new(name.into(), values)
+                }
+            };
+            df.with_column(series)?;
+        }
+        Ok(df)
+    }
+
+    /// 保存されているデータを結合後のデータにし、変わった記録を取り込みの履歴に残す
+    fn apply(
+        &mut self,
+        df: &DataFrame,
+        sources: Vec<ImportSource>,
+    ) -> StoreResult<Option<ImportBatch>> {
+        let columns: Vec<(&str, ColumnType)> = df
+            .get_columns()
+            .iter()
+            .filter(|column| !CORE_COLUMNS.contains(&column.name().as_str()))
+            .map(|column| (column.name().as_str(), ColumnType::of(column.dtype())))
+            .collect();
+        let mut stored: BTreeMap<EntryKey, ConditionEntry> = self
+            .read_entries(None, None)?
+            .into_iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+        let mut changes = vec![];
+        for entry in frame_entries(df, &columns)? {
+            match stored.remove(&entry.key()) {
+                Some(before) if before == entry => {}
+                before => changes.push((entry.key(), before, Some(entry))),
+            }
+        }
+        // 結合で無くなった記録 (日付が空欄になった行など)
+        changes.extend(
+            stored
+                .into_iter()
+                .map(|(key, before)| (key, Some(before), None)),
+        );
+
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM columns", [])?;
+        for (i, (name, dtype)) in columns.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO columns (position, name, dtype) VALUES (?1, ?2, ?3)",
+                params![i, name, dtype.as_str()],
+            )?;
+        }
+        let batch = write_changes(&tx, changes, sources, ImportKind::Files)?;
+        tx.commit()?;
+        Ok(batch)
+    }
+
+    fn revert(&mut self, id: i64) -> StoreResult<RollbackSummary> {
+        let tx = self.conn.transaction()?;
+        let changes = {
+            let mut stmt = tx.prepare(
+                "SELECT date, person, before, after FROM import_changes WHERE import_id = ?1",
+            )?;
+            let rows = stmt.query_map(params![id], |row| {
+                Ok((
+                    (row.get(0)?, row.get(1)?),
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<(EntryKey, _, _)>, _>>()?
+        };
+        let mut restored = 0;
+        let mut skipped = vec![];
+        for (key, before, after) in changes {
+            let before: Option<ConditionEntry> =
+                before.as_deref().map(serde_json::from_str).transpose()?;
+            let after: Option<ConditionEntry> =
+                after.as_deref().map(serde_json::from_str).transpose()?;
+            let current = read_entry(&tx, &key)?;
+            if current != after {
+                skipped.extend(current.or(after));
+                continue;
+            }
+            write_entry(&tx, &key, before.as_ref())?;
+            restored += 1;
+        }
+        tx.execute(
+            "UPDATE imports SET rolled_back_at = ?1 WHERE id = ?2",
+            params![Local::now(), id],
+        )?;
+        tx.commit()?;
+        skipped.sort_by_key(ConditionEntry::key);
+        Ok(RollbackSummary {
+            id,
+            restored,
+            skipped,
+        })
+    }
+}
+
+fn entry_row(row: &rusqlite::Row) -> rusqlite::Result<EntryRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+    ))
+}
+
+fn read_entry(conn: &Connection, key: &EntryKey) -> StoreResult<Option<ConditionEntry>> {
+    conn.query_row(
+        &format!("SELECT {ENTRY_COLUMNS} FROM entries WHERE date = ?1 AND person = ?2"),
+        params![key.0, key.1],
+        entry_row,
+    )
+    .optional()?
+    .map(ConditionEntry::from_row)
+    .transpose()
+}
+
+/// 記録を変更し、変更前の値と共に取り込みの履歴に残す (変更が無ければ何もしない)
+fn write_changes(
+    conn: &Connection,
+    changes: Vec<(EntryKey, Option<ConditionEntry>, Option<ConditionEntry>)>,
+    sources: Vec<ImportSource>,
+    kind: ImportKind,
+) -> StoreResult<Option<ImportBatch>> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    let imported_at = Local::now();
+    let added = changes
+        .iter()
+        .filter(|(_, before, _)| before.is_none())
+        .count();
+    let changed = changes.len() - added;
+    conn.execute(
+        "INSERT INTO imports (imported_at, kind, sources, added, changed)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            imported_at,
+            kind.as_str(),
+            serde_json::to_string(&sources)?,
+            added,
+            changed
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    for (key, before, after) in &changes {
+        write_entry(conn, key, after.as_ref())?;
+        conn.execute(
+            "INSERT INTO import_changes (import_id, date, person, before, after)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                key.0,
+                key.1,
+                before.as_ref().map(serde_json::to_string).transpose()?,
+                after.as_ref().map(serde_json::to_string).transpose()?,
+            ],
+        )?;
+    }
+    Ok(Some(ImportBatch {
+        id,
+        imported_at,
+        kind,
+        sources,
+        added,
+        changed,
+        rolled_back_at: None,
+    }))
+}
+
+/// 記録を書き込む (Noneなら削除する)
+fn write_entry(
+    conn: &Connection,
+    key: &EntryKey,
+    entry: Option<&ConditionEntry>,
+) -> StoreResult<()> {
+    match entry {
+        Some(entry) => conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO entries ({ENTRY_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5)"
+            ),
+            params![
+                key.0,
+                key.1,
+                entry.condition,
+                entry.comment,
+                serde_json::to_string(&entry.extra)?,
+            ],
+        )?,
+        None => conn.execute(
+            "DELETE FROM entries WHERE date = ?1 AND person = ?2",
+            params![key.0, key.1],
+        )?,
+    };
+    Ok(())
+}
+
+/// データの各行の日付と人 (日付が空欄の行はNone)
+fn frame_keys(df: &DataFrame) -> PolarsResult<Vec<Option<EntryKey>>> {
+    let dates = df.column(DATE_COLUMN)?.date()?;
+    let persons = match df.column(PERSON_COLUMN) {
+        Ok(persons) => Some(persons.str()?),
+        Err(_) => None,
+    };
+    Ok(dates
+        .as_date_iter()
+        .enumerate()
+        .map(|(i, date)| {
+            let person = persons
+                .and_then(|persons| persons.get(i))
+                .unwrap_or_default();
+            Some((date?, person.to_string()))
+        })
+        .collect())
+}
+
+/// データの各行を保存する記録に変換する (日付が空欄の行は保存しない)
+fn frame_entries(
+    df: &DataFrame,
+    columns: &[(&str, ColumnType)],
+) -> PolarsResult<Vec<ConditionEntry>> {
+    let dates = df.column(DATE_COLUMN)?.date()?;
+    let conditions = df.column(CONDITION_COLUMN)?.cast(&DataType::Int32)?;
+    let conditions = conditions.i32()?;
+    let comments = df.column(COMMENT_COLUMN)?.str()?;
+    let persons = match df.column(PERSON_COLUMN) {
+        Ok(persons) => Some(persons.str()?),
+        Err(_) => None,
+    };
+    let extra_values = columns
+        .iter()
+        .map(|&(name, dtype)| Ok((name, json_values(df.column(name)?, dtype)?)))
+        .collect::<PolarsResult<Vec<_>>>()?;
+
+    let mut entries = vec![];
+    for (i, date) in dates.as_date_iter().enumerate() {
+        let Some(date) = date else {
+            continue;
+        };
+        let extra = extra_values
+            .iter()
+            .filter_map(|(name, values)| Some((name.to_string(), values[i].clone()?)))
+            .collect();
+        entries.push(ConditionEntry {
+            date,
+            person: persons
+                .and_then(|persons| persons.get(i))
+                .filter(|person| !person.is_empty())
+                .map(String::from),
+            condition: conditions.get(i),
+            comment: comments.get(i).map(String::from),
+            extra,
+        });
+    }
+    Ok(entries)
+}
+
+fn json_values(column: &Column, dtype: ColumnType) -> PolarsResult<Vec<Option<Value>>> {
+    let values = match dtype {
+        ColumnType::Float => column
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+        ColumnType::Bool => column
+            .bool()?
+            .into_iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+        ColumnType::Date => column
+            .date()?
+            .as_date_iter()
+            .map(|v| v.map(|date| Value::from(date.to_string())))
+            .collect(),
+        ColumnType::String => column
+            .cast(&DataType::String)?
+            .str()?
+            .into_iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+    };
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    fn condition_data(df: DataFrame) -> ConditionData {
+        ConditionData {
+            ldf: df.lazy(),
+            conflicts: vec![],
+            diagnostics: vec![],
+            import: None,
+        }
+    }
+
+    #[test]
+    fn test_import_is_kept_after_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data").join(STORE_FILE);
+        let mut store = ConditionStore::open(&path).unwrap();
+        let excel_df = df!(
+            "日付" => [date(27), date(28)],
+            "体調" => [Some(3i32), None],
+            "コメント" => [Some("前回"), None],
+            "睡眠時間" => [Some(7.5f64), None],
+            "服薬" => [Some(true), Some(false)]
+        )
+        .unwrap();
+        store
+            .import(condition_data(excel_df), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        let csv_df = df!(
+            "日付" => [date(28), date(29)],
+            "体調" => [4i32, 2],
+            "コメント" => [None::<&str>, Some("頭痛")]
+        )
+        .unwrap();
+        let merged = store
+            .import(condition_data(csv_df), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        assert_eq!(merged.ldf.collect().unwrap().height(), 3);
+        drop(store);
+
+        let store = ConditionStore::open(&path).unwrap();
+        let expected_df = df!(
+            "日付" => [date(27), date(28), date(29)],
+            "体調" => [3i32, 4, 2],
+            "コメント" => [Some("前回"), None, Some("頭痛")],
+            "睡眠時間" => [Some(7.5f64), None, None],
+            "服薬" => [Some(true), Some(false), None]
+        )
+        .unwrap();
+        assert!(store.load().unwrap().equals_missing(&expected_df));
+
+        let entries = store.entries(Some(date(28)), None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].date, date(28));
+        assert_eq!(entries[0].extra["服薬"], Value::Bool(false));
+        assert!(!entries[0].extra.contains_key("睡眠時間"));
+        assert_eq!(store.entries(None, Some(date(27))).unwrap().len(), 1);
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_merge_does_not_save() {
+        let mut store = ConditionStore::open_in_memory().unwrap();
+        let stored_df = df!(
+            "日付" => [date(27)],
+            "人" => ["愛"],
+            "体調" => [3i32],
+            "コメント" => [None::<&str>]
+        )
+        .unwrap();
+        store
+            .import(condition_data(stored_df), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        let csv_df = df!(
+            "日付" => [date(27), date(27)],
+            "人" => ["愛", "蓮"],
+            "体調" => [5i32, 1],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let merged = store
+            .merge(condition_data(csv_df), MergeStrategy::PreferExcel)
+            .unwrap();
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.ldf.collect().unwrap().height(), 2);
+
+        let entries = store.entries(None, None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].person.as_deref(), Some("愛"));
+        assert_eq!(entries[0].condition, Some(3));
+    }
+
+    #[test]
+    fn test_rollback_import() {
+        let temp_dir = tempdir().unwrap();
+        let csv_path = temp_dir.path().join("RhythmCareData.csv");
+        fs::write(&csv_path, "abc").unwrap();
+        let source = ImportSource::read(&csv_path).unwrap();
+        assert_eq!(
+            source.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let mut store = ConditionStore::open_in_memory().unwrap();
+        let import = |store: &mut ConditionStore, df: DataFrame, sources| {
+            store
+                .import(condition_data(df), MergeStrategy::PreferCsv, sources)
+                .unwrap()
+                .import
+                .unwrap()
+        };
+        let first_df =
+            df!("日付" => [date(27)], "体調" => [3i32], "コメント" => [None::<&str>]).unwrap();
+        import(&mut store, first_df, vec![]);
+        let wrong_df = df!(
+            "日付" => [date(27), date(28)],
+            "体調" => [5i32, 2],
+            "コメント" => [None::<&str>, None]
+        )
+        .unwrap();
+        let wrong = import(&mut store, wrong_df, vec![source.clone()]);
+        assert_eq!((wrong.added, wrong.changed), (1, 1));
+        assert_eq!(wrong.kind, ImportKind::Files);
+        assert_eq!(wrong.sources, [source]);
+        let later_df =
+            df!("日付" => [date(28)], "体調" => [4i32], "コメント" => [None::<&str>]).unwrap();
+        import(&mut store, later_df.clone(), vec![]);
+        // 何も変わらない取り込みは履歴に残さない
+        let unchanged = store
+            .import(condition_data(later_df), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        assert!(unchanged.import.is_none());
+
+        let summary = store.rollback(wrong.id).unwrap();
+        assert_eq!(summary.restored, 1);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].date, date(28));
+        assert_eq!(summary.skipped[0].condition, Some(4));
+        let conditions: Vec<_> = store
+            .entries(None, None)
+            .unwrap()
+            .iter()
+            .map(|entry| (entry.date, entry.condition))
+            .collect();
+        assert_eq!(conditions, [(date(27), Some(3)), (date(28), Some(4))]);
+
+        let imports = store.imports().unwrap();
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[1].id, wrong.id);
+        assert!(imports[1].rolled_back_at.is_some());
+        assert!(store.rollback(wrong.id).is_err());
+        assert!(store.rollback(100).is_err());
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_edit_entries() {
+        let mut store = ConditionStore::open_in_memory().unwrap();
+        let scale = ScoreScale::default();
+        let stored_df = df!(
+            "日付" => [date(27)],
+            "体調" => [3i32],
+            "コメント" => [None::<&str>],
+            "睡眠時間" => [7.5f64]
+        )
+        .unwrap();
+        store
+            .import(condition_data(stored_df), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        let input = |day, condition, comment: &str| EntryInput {
+            date: date(day),
+            person: None,
+            condition,
+            comment: Some(comment.into()),
+        };
+
+        let added = store
+            .add_entry(input(28, Some(2), " 頭痛 "), &scale)
+            .unwrap();
+        assert_eq!(added.comment.as_deref(), Some("頭痛"));
+        let err = store.add_entry(input(28, Some(4), ""), &scale).unwrap_err();
+        assert!(matches!(err, ConditionError::EntryExists { .. }));
+        let err = store.add_entry(input(29, Some(6), ""), &scale).unwrap_err();
+        assert!(matches!(err, ConditionError::InvalidEntry { .. }));
+        let tomorrow = Local::now().date_naive().succ_opt().unwrap();
+        let err = store
+            .add_entry(
+                EntryInput {
+                    date: tomorrow,
+                    ..input(1, None, "")
+                },
+                &scale,
+            )
+            .unwrap_err();
+        assert!(matches!(err, ConditionError::InvalidEntry { .. }));
+
+        let updated = store.update_entry(input(27, Some(4), ""), &scale).unwrap();
+        assert_eq!(updated.comment, None);
+        assert_eq!(updated.extra["睡眠時間"], Value::from(7.5));
+        let err = store
+            .update_entry(input(30, Some(4), ""), &scale)
+            .unwrap_err();
+        assert!(matches!(err, ConditionError::EntryNotFound { .. }));
+
+        store.delete_entry(date(28), None).unwrap();
+        let err = store.delete_entry(date(28), None).unwrap_err();
+        assert!(matches!(err, ConditionError::EntryNotFound { .. }));
+        let entries = store.entries(None, None).unwrap();
+        assert_eq!(entries, [updated]);
+
+        // 手での変更も取り込みと同じく取り消せる
+        let imports = store.imports().unwrap();
+        assert_eq!(imports.len(), 4);
+        assert!(imports[..3]
+            .iter()
+            .all(|batch| batch.kind == ImportKind::Manual));
+        assert_eq!(imports[3].kind, ImportKind::Files);
+        store.rollback(imports[0].id).unwrap();
+        assert_eq!(store.entries(None, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_manual_edit_survives_csv_reimport() {
+        let mut store = ConditionStore::open_in_memory().unwrap();
+        let scale = ScoreScale::default();
+        let csv_df = || {
+            df!(
+                "日付" => [date(27), date(28)],
+                "体調" => [3i32, 4i32],
+                "コメント" => [None::<&str>, Some("CSV")]
+            )
+            .unwrap()
+        };
+        store
+            .import(condition_data(csv_df()), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        let edited = store
+            .update_entry(
+                EntryInput {
+                    date: date(27),
+                    person: None,
+                    condition: Some(5),
+                    comment: Some("手で修正".into()),
+                },
+                &scale,
+            )
+            .unwrap();
+
+        let reimport_df = df!(
+            "日付" => [date(27), date(28), date(29)],
+            "体調" => [3i32, 2i32, 1i32],
+            "コメント" => [None::<&str>, Some("CSV"), None]
+        )
+        .unwrap();
+        let merged = store
+            .import(
+                condition_data(reimport_df),
+                MergeStrategy::PreferCsv,
+                vec![],
+            )
+            .unwrap();
+        let entries = store.entries(None, None).unwrap();
+        assert_eq!(entries[0], edited);
+        assert_eq!(entries[1].condition, Some(2));
+        assert_eq!(entries[2].date, date(29));
+        let batch = merged.import.unwrap();
+        assert_eq!((batch.added, batch.changed), (1, 1));
+        // 残した手での修正は競合として報告する
+        let conflicts: Vec<_> = merged
+            .conflicts
+            .iter()
+            .filter(|conflict| conflict.date == date(27))
+            .map(|conflict| {
+                (
+                    conflict.column.as_str(),
+                    conflict.csv.as_deref(),
+                    conflict.resolved.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                ("体調", Some("3"), Some("5")),
+                ("コメント", None, Some("手で修正"))
+            ]
+        );
+
+        // 手での修正を取り消せば、再び読み込んだ値で更新される
+        let manual = store
+            .imports()
+            .unwrap()
+            .into_iter()
+            .find(|batch| batch.kind == ImportKind::Manual)
+            .unwrap();
+        store.rollback(manual.id).unwrap();
+        store
+            .import(condition_data(csv_df()), MergeStrategy::PreferCsv, vec![])
+            .unwrap();
+        let entries = store.entries(None, None).unwrap();
+        assert_eq!(entries[0].condition, Some(3));
+        assert_eq!(entries[1].condition, Some(4));
+    }
+}
@@ -0,0 +1,1398 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use ::zip::write::SimpleFileOptions;
+use ::zip::{CompressionMethod, ZipArchive, ZipWriter};
+use polars::prelude::*;
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::schema::{DATE_COLUMN, PERSON_COLUMN};
+
+const RELATIONSHIPS_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+
+/// 新しいレポートの書式の後ろに加える、前回のレポートの書式の順番
+const STYLE_SECTIONS: [&str; 6] = ["numFmts", "fonts", "fills", "borders", "cellXfs", "dxfs"];
+
+/// エクセルで利用者が定義できる表示形式の番号の始まり
+const FIRST_CUSTOM_NUM_FMT: u32 = 164;
+
+type PackageResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// 前回のレポートのdataシートに無かった行、または値が変わった行を含む年
+///
+/// 日付(人の列があれば日付と人)で行を対応させ、両方にある列の値を比べる。
+pub(crate) fn changed_years(
+    previous_df: &DataFrame,
+    merged_df: &DataFrame,
+) -> PolarsResult<Vec<i32>> {
+    let has_person = [previous_df, merged_df]
+        .iter()
+        .any(|df| df.get_column_index(PERSON_COLUMN).is_some());
+    let with_person = |df: &DataFrame| {
+        let ldf = df.clone().lazy();
+        match df.get_column_index(PERSON_COLUMN) {
+            None if has_person => {
+                ldf.with_column(lit(NULL).cast(DataType::String).alias(PERSON_COLUMN))
+            }
+            _ => ldf,
+        }
+    };
+    let keys: Vec<Expr> = if has_person {
+        vec![col(DATE_COLUMN), col(PERSON_COLUMN)]
+    } else {
+        vec![col(DATE_COLUMN)]
+    };
+
+    // 人が空欄の行同士も同じ人として対応させる
+    let mut join_args = JoinArgs::new(JoinType::Left).with_suffix(Some("_前回".into()));
+    join_args.join_nulls = true;
+    let previous_ldf = with_person(previous_df).with_column(lit(true).alias("前回の行"));
+    let mut changed = col("前回の行").is_null();
+    for name in merged_df.get_column_names() {
+        if name == DATE_COLUMN
+            || name == PERSON_COLUMN
+            || previous_df.get_column_index(name).is_none()
+        {
+            continue;
+        }
+        changed = changed.or(col(name.clone())
+            .cast(DataType::String)
+            .neq_missing(col(format!("{name}_前回")).cast(DataType::String)));
+    }
+    let years_df = with_person(merged_df)
+        .join(previous_ldf, &keys, &keys, join_args)
+        .filter(changed)
+        .select([col(DATE_COLUMN)
+            .dt()
+            .year()
+            .unique()
+            .sort(Default::default())])
+        .collect()?;
+    Ok(years_df
+        .column(DATE_COLUMN)?
+        .i32()?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// 前回のレポートにあり、新しいレポートに無いシートのうち`keep`が真のものを書式やメモごと移して保存する
+///
+/// 新しいレポートと同じ名前のシートは新しいものに置き換え、シートの並びは前回のものを保つ。
+/// 移したシートの名前を返す。
+pub(crate) fn carry_over_sheets(
+    previous: &Path,
+    generated: &Path,
+    output: &Path,
+    keep: impl Fn(&str) -> bool,
+) -> ConditionResult<Vec<String>> {
+    let old = Package::open(previous).map_err(|e| ConditionError::excel_package(previous, e))?;
+    let mut new = Package::open(generated).map_err(|e| ConditionError::Internal(e.to_string()))?;
+    let carried = Splice::new(&old, &new)
+        .and_then(|splice| splice.run(&mut new, keep))
+        .map_err(|e| ConditionError::excel_package(previous, e))?;
+    new.save(output)
+        .map_err(|e| ConditionError::save(output, e))?;
+    Ok(carried)
+}
+
+/// ファイルに保存されている文書のプロパティ(ユーザー設定)の値 (無ければNone)
+pub(crate) fn custom_property(path: &Path, name: &str) -> ConditionResult<Option<String>> {
+    let read = || -> PackageResult<Option<String>> {
+        let package = Package::open(path)?;
+        let part = read_rels(&package, "")?
+            .into_iter()
+            .find(|rel| rel.rel_type.ends_with("/custom-properties"))
+            .map(|rel| resolve("", &rel.target));
+        let Some(xml) = part.as_deref().and_then(|part| package.get(part)) else {
+            return Ok(None);
+        };
+        for property in child_elements(xml, b"Properties")? {
+            if root_attribute(property.as_bytes(), "name")?.as_deref() == Some(name) {
+                // <property><vt:lpwstr>値</vt:lpwstr></property>
+                let value = inner_xml(inner_xml(&property));
+                return Ok(Some(quick_xml::escape::unescape(value)?.into_owned()));
+            }
+        }
+        Ok(None)
+    };
+    read().map_err(|e| ConditionError::excel_package(path, e))
+}
+
+/// xlsxファイル(zip)の中のファイルを、順番を保ったまま読み書きする
+struct Package {
+    parts: Vec<(String, Vec<u8>)>,
+}
+
+impl Package {
+    fn open(path: &Path) -> PackageResult<Self> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut parts = vec![];
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut data = vec![];
+            file.read_to_end(&mut data)?;
+            parts.push((file.name().to_string(), data));
+        }
+        Ok(Self { parts })
+    }
+
+    fn get(&self, name: &str) -> Option<&[u8]> {
+        self.parts
+            .iter()
+            .find(|(part, _)| part == name)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    fn require(&self, name: &str) -> PackageResult<&[u8]> {
+        self.get(name)
+            .ok_or_else(|| format!("`{name}` is missing from the workbook").into())
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn set(&mut self, name: &str, data: Vec<u8>) {
+        match self.parts.iter_mut().find(|(part, _)| part == name) {
+            Some((_, old)) => *old = data,
+            None => self.parts.push((name.to_string(), data)),
+        }
+    }
+
+    /// 一時ファイルに書いてから置き換え、途中で失敗しても保存先を壊さない
+    fn save(&self, path: &Path) -> PackageResult<()> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        let mut zip = ZipWriter::new(temp.as_file_mut());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, data) in &self.parts {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(data)?;
+        }
+        zip.finish()?;
+        temp.persist(path)?;
+        Ok(())
+    }
+}
+
+struct Relationship {
+    id: String,
+    rel_type: String,
+    target: String,
+    external: bool,
+}
+
+/// ワークブックのシートと、その中身のファイル
+struct SheetEntry {
+    name: String,
+    /// workbook.xmlのsheet要素
+    element: String,
+    rel_type: String,
+    part: String,
+}
+
+/// 拡張子毎の既定と、ファイル毎のContent-Type
+#[derive(Default)]
+struct ContentTypes {
+    defaults: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+}
+
+impl ContentTypes {
+    fn parse(xml: &[u8]) -> PackageResult<Self> {
+        let mut types = Self::default();
+        for element in child_elements(xml, b"Types")? {
+            let content_type =
+                root_attribute(element.as_bytes(), "ContentType")?.unwrap_or_default();
+            if let Some(extension) = root_attribute(element.as_bytes(), "Extension")? {
+                types
+                    .defaults
+                    .insert(extension.to_lowercase(), content_type);
+            } else if let Some(part) = root_attribute(element.as_bytes(), "PartName")? {
+                types
+                    .overrides
+                    .insert(part.trim_start_matches('/').to_string(), content_type);
+            }
+        }
+        Ok(types)
+    }
+
+    fn get(&self, part: &str) -> Option<&str> {
+        self.overrides
+            .get(part)
+            .or_else(|| self.defaults.get(&extension(part)))
+            .map(String::as_str)
+    }
+}
+
+/// 前回のレポートから新しいレポートへシートを移す際の対応表
+struct Splice<'a> {
+    old: &'a Package,
+    old_types: ContentTypes,
+    new_types: ContentTypes,
+    /// [Content_Types].xmlに加える要素
+    added_types: Vec<String>,
+    shared_strings: Vec<String>,
+    styles: StyleOffsets,
+    merged_styles: Option<(String, Vec<u8>)>,
+    /// 前回のレポートのファイル名と、移した先のファイル名
+    copied: HashMap<String, String>,
+    next_table_id: u32,
+    table_names: HashSet<String>,
+}
+
+#[derive(Default)]
+struct StyleOffsets {
+    font: usize,
+    fill: usize,
+    border: usize,
+    xf: usize,
+    dxf: usize,
+    num_fmts: HashMap<u32, u32>,
+}
+
+impl<'a> Splice<'a> {
+    fn new(old: &'a Package, new: &Package) -> PackageResult<Self> {
+        let old_workbook = workbook_part(old)?;
+        let new_workbook = workbook_part(new)?;
+        let old_rels = read_rels(old, &old_workbook)?;
+        let new_rels = read_rels(new, &new_workbook)?;
+        let part_of = |rels: &[Relationship], workbook: &str, rel_type: &str| {
+            rels.iter()
+                .find(|rel| rel.rel_type.ends_with(rel_type))
+                .map(|rel| resolve(workbook, &rel.target))
+        };
+
+        let shared_strings = match part_of(&old_rels, &old_workbook, "/sharedStrings") {
+            Some(part) => child_elements(old.require(&part)?, b"sst")?
+                .iter()
+                .map(|si| inner_xml(si).to_string())
+                .collect(),
+            None => vec![],
+        };
+        let (styles, merged_styles) = match (
+            part_of(&old_rels, &old_workbook, "/styles"),
+            part_of(&new_rels, &new_workbook, "/styles"),
+        ) {
+            (Some(old_part), Some(new_part)) => {
+                let (xml, offsets) =
+                    merge_styles(old.require(&old_part)?, new.require(&new_part)?)?;
+                (offsets, Some((new_part, xml)))
+            }
+            _ => (StyleOffsets::default(), None),
+        };
+
+        // 表のIDと名前はワークブック全体で重ならないようにする
+        let mut next_table_id = 1;
+        let mut table_names = HashSet::new();
+        for (part, data) in &new.parts {
+            if !part.starts_with("xl/tables/") || !part.ends_with(".xml") {
+                continue;
+            }
+            if let Some(id) = root_attribute(data, "id")? {
+                next_table_id = next_table_id.max(id.parse::<u32>()? + 1);
+            }
+            for key in ["name", "displayName"] {
+                if let Some(name) = root_attribute(data, key)? {
+                    table_names.insert(name.to_lowercase());
+                }
+            }
+        }
+
+        Ok(Self {
+            old,
+            old_types: ContentTypes::parse(old.require("[Content_Types].xml")?)?,
+            new_types: ContentTypes::parse(new.require("[Content_Types].xml")?)?,
+            added_types: vec![],
+            shared_strings,
+            styles,
+            merged_styles,
+            copied: HashMap::new(),
+            next_table_id,
+            table_names,
+        })
+    }
+
+    fn run(mut self, new: &mut Package, keep: impl Fn(&str) -> bool) -> PackageResult<Vec<String>> {
+        let old_sheets = read_sheets(self.old)?;
+        let new_sheets = read_sheets(new)?;
+        let new_names: HashSet<&str> = new_sheets.iter().map(|sheet| sheet.name.as_str()).collect();
+        let carried: Vec<&SheetEntry> = old_sheets
+            .iter()
+            .filter(|sheet| !new_names.contains(sheet.name.as_str()) && keep(&sheet.name))
+            .collect();
+        if carried.is_empty() {
+            return Ok(vec![]);
+        }
+        if let Some((part, xml)) = self.merged_styles.take() {
+            new.set(&part, xml);
+        }
+
+        let workbook = workbook_part(new)?;
+        let mut workbook_rels = read_rels(new, &workbook)?;
+        let first_rel_id = next_number(workbook_rels.iter().map(|rel| rel.id.as_str()), "rId");
+        let first_sheet_id = new_sheets
+            .iter()
+            .filter_map(|sheet| {
+                root_attribute(sheet.element.as_bytes(), "sheetId")
+                    .ok()
+                    .flatten()
+            })
+            .filter_map(|id| id.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut carried_elements = HashMap::new();
+        for (i, sheet) in carried.iter().enumerate() {
+            let part = self.copy_part(new, &sheet.part)?;
+            let rel_id = format!("rId{}", first_rel_id + i);
+            workbook_rels.push(Relationship {
+                id: rel_id.clone(),
+                rel_type: sheet.rel_type.clone(),
+                target: relative_target(&workbook, &part),
+                external: false,
+            });
+            let mut element = BytesStart::new("sheet");
+            element.push_attribute(("name", sheet.name.as_str()));
+            let sheet_id = first_sheet_id + i;
+            element.push_attribute(("sheetId", sheet_id.to_string().as_str()));
+            if let Some(state) = root_attribute(sheet.element.as_bytes(), "state")? {
+                element.push_attribute(("state", state.as_str()));
+            }
+            element.push_attribute(("r:id", rel_id.as_str()));
+            carried_elements.insert(sheet.name.clone(), to_xml(Event::Empty(element))?);
+        }
+
+        // シートの並びは前回のものを保ち、新しく増えたシートは新しいレポートでの前のシートの後ろに置く
+        let old_names: Vec<&str> = old_sheets.iter().map(|sheet| sheet.name.as_str()).collect();
+        let new_order: Vec<&str> = new_sheets.iter().map(|sheet| sheet.name.as_str()).collect();
+        // 移さなかった前回のシートは並びからも除く
+        let kept_names: Vec<&str> = old_names
+            .iter()
+            .copied()
+            .filter(|name| carried_elements.contains_key(*name) || new_order.contains(name))
+            .collect();
+        let order = sheet_order(&kept_names, &new_order);
+        let mut elements = vec![];
+        for name in &order {
+            match new_sheets.iter().find(|sheet| sheet.name == *name) {
+                Some(sheet) => elements.push(sheet.element.clone()),
+                None => elements.push(carried_elements[name].clone()),
+            }
+        }
+
+        let old_workbook = workbook_part(self.old)?;
+        let defined_names = merge_defined_names(
+            self.old.require(&old_workbook)?,
+            &old_names,
+            new.require(&workbook)?,
+            &new_order,
+            &order,
+        )?;
+        let workbook_xml = rewrite_workbook(new.require(&workbook)?, &elements, &defined_names)?;
+        new.set(&workbook, workbook_xml);
+        new.set(&rels_path(&workbook), write_rels(&workbook_rels)?);
+
+        if !self.added_types.is_empty() {
+            let content_types = String::from_utf8(new.require("[Content_Types].xml")?.to_vec())?;
+            let end = content_types
+                .rfind("</Types>")
+                .ok_or("[Content_Types].xml has no closing tag")?;
+            let content_types = format!(
+                "{}{}{}",
+                &content_types[..end],
+                self.added_types.concat(),
+                &content_types[end..]
+            );
+            new.set("[Content_Types].xml", content_types.into_bytes());
+        }
+        Ok(carried.iter().map(|sheet| sheet.name.clone()).collect())
+    }
+
+    /// 前回のレポートのファイルを、関連するファイル(グラフ・メモ・画像など)ごと新しいレポートに移す
+    fn copy_part(&mut self, new: &mut Package, old_part: &str) -> PackageResult<String> {
+        if let Some(part) = self.copied.get(old_part) {
+            return Ok(part.clone());
+        }
+        let part = unique_part_name(new, old_part);
+        self.copied.insert(old_part.to_string(), part.clone());
+        // 中身を書く前に場所を確保し、関連するファイルと名前が重ならないようにする
+        new.set(&part, vec![]);
+
+        let content_type = self.old_types.get(old_part).unwrap_or_default().to_string();
+        let data = self.old.require(old_part)?;
+        let data = if content_type.ends_with("worksheet+xml") {
+            self.rewrite_sheet(data)?
+        } else if content_type.ends_with("table+xml") {
+            self.renumber_table(data)?
+        } else {
+            data.to_vec()
+        };
+
+        let old_rels = rels_path(old_part);
+        if self.old.contains(&old_rels) {
+            let mut rels = vec![];
+            for mut rel in read_rels(self.old, old_part)? {
+                if !rel.external {
+                    // ピボットテーブルはワークブック全体のキャッシュに依存するため移さない
+                    if rel.rel_type.ends_with("/pivotTable") {
+                        continue;
+                    }
+                    let target = resolve(old_part, &rel.target);
+                    if !self.old.contains(&target) {
+                        continue;
+                    }
+                    let copied = self.copy_part(new, &target)?;
+                    rel.target = relative_target(&part, &copied);
+                }
+                rels.push(rel);
+            }
+            new.set(&rels_path(&part), write_rels(&rels)?);
+        }
+        new.set(&part, data);
+        self.add_content_type(old_part, &part)?;
+        Ok(part)
+    }
+
+    fn add_content_type(&mut self, old_part: &str, part: &str) -> PackageResult<()> {
+        if let Some(content_type) = self.old_types.overrides.get(old_part) {
+            let mut element = BytesStart::new("Override");
+            element.push_attribute(("PartName", format!("/{part}").as_str()));
+            element.push_attribute(("ContentType", content_type.as_str()));
+            self.added_types.push(to_xml(Event::Empty(element))?);
+            self.new_types
+                .overrides
+                .insert(part.to_string(), content_type.clone());
+            return Ok(());
+        }
+        let extension = extension(part);
+        if self.new_types.defaults.contains_key(&extension) {
+            return Ok(());
+        }
+        if let Some(content_type) = self.old_types.defaults.get(&extension) {
+            let mut element = BytesStart::new("Default");
+            element.push_attribute(("Extension", extension.as_str()));
+            element.push_attribute(("ContentType", content_type.as_str()));
+            self.added_types.push(to_xml(Event::Empty(element))?);
+            self.new_types
+                .defaults
+                .insert(extension, content_type.clone());
+        }
+        Ok(())
+    }
+
+    /// 共有文字列をセル内の文字列に置き換え、書式の番号を新しいレポートでの番号にずらす
+    fn rewrite_sheet(&self, xml: &[u8]) -> PackageResult<Vec<u8>> {
+        let styles = &self.styles;
+        let mut reader = Reader::from_reader(xml);
+        let mut writer = Writer::new(vec![]);
+        // 共有文字列のセルの接頭辞 (名前空間の接頭辞があればそれに合わせる)
+        let mut shared_cell: Option<String> = None;
+        let mut in_value = false;
+        loop {
+            match reader.read_event()? {
+                Event::Eof => break,
+                Event::Start(e) if e.local_name().as_ref() == b"v" && shared_cell.is_some() => {
+                    in_value = true;
+                }
+                Event::Text(text) if in_value => {
+                    let index: usize = text.unescape()?.trim().parse()?;
+                    let value = self
+                        .shared_strings
+                        .get(index)
+                        .ok_or("shared string index is out of range")?;
+                    let prefix = shared_cell.as_deref().unwrap_or_default();
+                    write!(writer.get_mut(), "<{prefix}is>{value}</{prefix}is>")?;
+                }
+                Event::End(e) if in_value && e.local_name().as_ref() == b"v" => {
+                    in_value = false;
+                }
+                Event::Start(e) => {
+                    if e.local_name().as_ref() == b"c" {
+                        shared_cell = (attribute(&e, "t")?.as_deref() == Some("s"))
+                            .then(|| name_prefix(e.name().as_ref()));
+                    }
+                    writer.write_event(Event::Start(restyle(&e, styles)?))?;
+                }
+                Event::Empty(e) => writer.write_event(Event::Empty(restyle(&e, styles)?))?,
+                Event::End(e) => {
+                    if e.local_name().as_ref() == b"c" {
+                        shared_cell = None;
+                    }
+                    writer.write_event(Event::End(e))?;
+                }
+                event => writer.write_event(event)?,
+            }
+        }
+        Ok(writer.into_inner())
+    }
+
+    fn renumber_table(&mut self, xml: &[u8]) -> PackageResult<Vec<u8>> {
+        let id = self.next_table_id;
+        self.next_table_id += 1;
+        let name = root_attribute(xml, "displayName")?.unwrap_or_default();
+        let name = if name.is_empty() || self.table_names.contains(&name.to_lowercase()) {
+            let mut n = id;
+            while self.table_names.contains(&format!("table{n}")) {
+                n += 1;
+            }
+            format!("Table{n}")
+        } else {
+            name
+        };
+        self.table_names.insert(name.to_lowercase());
+        rewrite_root(xml, |key, value| {
+            Ok(Some(match key {
+                "id" => id.to_string(),
+                "name" | "displayName" => name.clone(),
+                _ => value.to_string(),
+            }))
+        })
+    }
+}
+
+/// セル・行・列の書式と条件付き書式の番号をずらし、シートの選択状態を外す
+fn restyle(e: &BytesStart, styles: &StyleOffsets) -> PackageResult<BytesStart<'static>> {
+    let element = e.local_name().as_ref().to_vec();
+    let shared_cell = element == b"c" && attribute(e, "t")?.as_deref() == Some("s");
+    map_attributes(e, |key, value| {
+        Ok(match (element.as_slice(), key) {
+            (b"c", "t") if shared_cell => Some("inlineStr".into()),
+            (b"c" | b"row", "s") | (b"col", "style") => Some(shift(value, styles.xf)?),
+            (_, "dxfId") => Some(shift(value, styles.dxf)?),
+            (b"sheetView", "tabSelected") => None,
+            _ => Some(value.to_string()),
+        })
+    })
+}
+
+fn shift(value: &str, offset: usize) -> PackageResult<String> {
+    Ok((value.parse::<usize>()? + offset).to_string())
+}
+
+/// 前回のレポートの書式を新しいレポートの書式の後ろに加え、番号のずれを返す
+fn merge_styles(old_xml: &[u8], new_xml: &[u8]) -> PackageResult<(Vec<u8>, StyleOffsets)> {
+    let mut new_counts = HashMap::new();
+    for section in STYLE_SECTIONS {
+        new_counts.insert(section, child_elements(new_xml, section.as_bytes())?.len());
+    }
+    let mut next_num_fmt = FIRST_CUSTOM_NUM_FMT;
+    for num_fmt in child_elements(new_xml, b"numFmts")? {
+        if let Some(id) = root_attribute(num_fmt.as_bytes(), "numFmtId")? {
+            next_num_fmt = next_num_fmt.max(id.parse::<u32>()? + 1);
+        }
+    }
+    let mut offsets = StyleOffsets {
+        font: new_counts["fonts"],
+        fill: new_counts["fills"],
+        border: new_counts["borders"],
+        xf: new_counts["cellXfs"],
+        dxf: new_counts["dxfs"],
+        num_fmts: HashMap::new(),
+    };
+
+    let mut extras: HashMap<&str, Vec<String>> = HashMap::new();
+    for num_fmt in child_elements(old_xml, b"numFmts")? {
+        let Some(id) = root_attribute(num_fmt.as_bytes(), "numFmtId")? else {
+            continue;
+        };
+        let id: u32 = id.parse()?;
+        if id < FIRST_CUSTOM_NUM_FMT {
+            continue;
+        }
+        offsets.num_fmts.insert(id, next_num_fmt);
+        let rewritten = rewrite_root(num_fmt.as_bytes(), |key, value| {
+            Ok(Some(match key {
+                "numFmtId" => next_num_fmt.to_string(),
+                _ => value.to_string(),
+            }))
+        })?;
+        extras
+            .entry("numFmts")
+            .or_default()
+            .push(String::from_utf8(rewritten)?);
+        next_num_fmt += 1;
+    }
+    for section in ["fonts", "fills", "borders", "dxfs"] {
+        extras.insert(section, child_elements(old_xml, section.as_bytes())?);
+    }
+    let mut xfs = vec![];
+    for xf in child_elements(old_xml, b"cellXfs")? {
+        let rewritten = rewrite_root(xf.as_bytes(), |key, value| {
+            Ok(Some(match key {
+                "fontId" => shift(value, offsets.font)?,
+                "fillId" => shift(value, offsets.fill)?,
+                "borderId" => shift(value, offsets.border)?,
+                "numFmtId" => {
+                    let id: u32 = value.parse()?;
+                    offsets.num_fmts.get(&id).unwrap_or(&id).to_string()
+                }
+                // セルのスタイル(標準・良いなど)は新しいレポートに無いため標準に戻す
+                "xfId" => "0".into(),
+                _ => value.to_string(),
+            }))
+        })?;
+        xfs.push(String::from_utf8(rewritten)?);
+    }
+    extras.insert("cellXfs", xfs);
+
+    let section_xml = |section: &str, extra: &[String]| -> PackageResult<String> {
+        let mut start = BytesStart::new(section);
+        start.push_attribute(("count", extra.len().to_string().as_str()));
+        Ok(format!(
+            "{}{}</{section}>",
+            to_xml(Event::Start(start))?,
+            extra.concat()
+        ))
+    };
+    let mut reader = Reader::from_reader(new_xml);
+    let mut writer = Writer::new(vec![]);
+    let mut seen = HashSet::new();
+    loop {
+        let event = reader.read_event()?;
+        let name = match &event {
+            Event::Start(e) | Event::Empty(e) => e.local_name().as_ref().to_vec(),
+            Event::End(e) => e.local_name().as_ref().to_vec(),
+            _ => vec![],
+        };
+        let name = String::from_utf8(name)?;
+        let section = STYLE_SECTIONS
+            .iter()
+            .find(|&&section| section == name)
+            .copied();
+        let extra = section
+            .and_then(|section| extras.get(section))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match event {
+            Event::Eof => break,
+            Event::Start(e) if section.is_some() => {
+                let count = new_counts[name.as_str()] + extra.len();
+                writer.write_event(Event::Start(with_count(&e, count)?))?;
+            }
+            Event::End(e) if section.is_some() => {
+                writer.get_mut().write_all(extra.concat().as_bytes())?;
+                writer.write_event(Event::End(e))?;
+                seen.insert(name);
+            }
+            Event::Empty(e) if section.is_some() => {
+                if extra.is_empty() {
+                    writer.write_event(Event::Empty(e))?;
+                } else {
+                    writer
+                        .get_mut()
+                        .write_all(section_xml(&name, extra)?.as_bytes())?;
+                }
+                seen.insert(name);
+            }
+            Event::Start(e) if name == "styleSheet" => {
+                writer.write_event(Event::Start(e))?;
+                // 表示形式の一覧は先頭に置く
+                let num_fmts = extras.get("numFmts").map(Vec::as_slice).unwrap_or_default();
+                if new_counts["numFmts"] == 0 && !num_fmts.is_empty() {
+                    writer
+                        .get_mut()
+                        .write_all(section_xml("numFmts", num_fmts)?.as_bytes())?;
+                    seen.insert("numFmts".into());
+                }
+            }
+            event => {
+                // 条件付き書式の一覧はcellStylesの後、tableStylesの前に置く
+                let before_dxfs = matches!(&event, Event::Start(_) | Event::Empty(_))
+                    && name == "tableStyles"
+                    || matches!(&event, Event::End(_)) && name == "styleSheet";
+                if before_dxfs && !seen.contains("dxfs") && !extras["dxfs"].is_empty() {
+                    writer
+                        .get_mut()
+                        .write_all(section_xml("dxfs", &extras["dxfs"])?.as_bytes())?;
+                    seen.insert("dxfs".into());
+                }
+                writer.write_event(event)?;
+            }
+        }
+    }
+    Ok((writer.into_inner(), offsets))
+}
+
+fn with_count(e: &BytesStart, count: usize) -> PackageResult<BytesStart<'static>> {
+    map_attributes(e, |key, value| {
+        Ok(Some(match key {
+            "count" => count.to_string(),
+            _ => value.to_string(),
+        }))
+    })
+}
+
+/// 前回と新しいレポートの名前の定義(印刷範囲やフィルタなど)を、最終的なシートの並びに合わせる
+fn merge_defined_names(
+    old_workbook: &[u8],
+    old_sheets: &[&str],
+    new_workbook: &[u8],
+    new_sheets: &[&str],
+    order: &[String],
+) -> PackageResult<Vec<String>> {
+    let position = |name: &str| order.iter().position(|sheet| sheet == name);
+    let relocate = |element: &str, sheets: &[&str]| -> PackageResult<Option<String>> {
+        let Some(index) = root_attribute(element.as_bytes(), "localSheetId")? else {
+            return Ok(Some(element.to_string()));
+        };
+        let Some(index) = sheets
+            .get(index.parse::<usize>()?)
+            .and_then(|name| position(name))
+        else {
+            return Ok(None);
+        };
+        let rewritten = rewrite_root(element.as_bytes(), |key, value| {
+            Ok(Some(match key {
+                "localSheetId" => index.to_string(),
+                _ => value.to_string(),
+            }))
+        })?;
+        Ok(Some(String::from_utf8(rewritten)?))
+    };
+
+    let mut names = vec![];
+    let mut global_names = HashSet::new();
+    for element in child_elements(new_workbook, b"definedNames")? {
+        if root_attribute(element.as_bytes(), "localSheetId")?.is_none() {
+            global_names.insert(root_attribute(element.as_bytes(), "name")?);
+        }
+        names.extend(relocate(&element, new_sheets)?);
+    }
+    for element in child_elements(old_workbook, b"definedNames")? {
+        match root_attribute(element.as_bytes(), "localSheetId")? {
+            // 作り直したシートの定義は新しいレポートのものを使う
+            Some(index) => {
+                let carried = old_sheets
+                    .get(index.parse::<usize>()?)
+                    .is_some_and(|name| !new_sheets.contains(name));
+                if carried {
+                    names.extend(relocate(&element, old_sheets)?);
+                }
+            }
+            None => {
+                if !global_names.contains(&root_attribute(element.as_bytes(), "name")?) {
+                    names.push(element);
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// workbook.xmlのシートの一覧と名前の定義を置き換える
+fn rewrite_workbook(
+    xml: &[u8],
+    sheets: &[String],
+    defined_names: &[String],
+) -> PackageResult<Vec<u8>> {
+    let defined_names_xml = if defined_names.is_empty() {
+        String::new()
+    } else {
+        format!("<definedNames>{}</definedNames>", defined_names.concat())
+    };
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(vec![]);
+    let mut skipping = false;
+    let mut names_written = false;
+    loop {
+        let event = reader.read_event()?;
+        let name = match &event {
+            Event::Start(e) | Event::Empty(e) => e.local_name().as_ref().to_vec(),
+            Event::End(e) => e.local_name().as_ref().to_vec(),
+            _ => vec![],
+        };
+        match event {
+            Event::Eof => break,
+            Event::Start(e) if name == b"sheets" => {
+                writer.write_event(Event::Start(e))?;
+                writer.get_mut().write_all(sheets.concat().as_bytes())?;
+                skipping = true;
+            }
+            Event::End(e) if name == b"sheets" => {
+                writer.write_event(Event::End(e))?;
+                skipping = false;
+            }
+            Event::Start(_) if name == b"definedNames" => {
+                writer.get_mut().write_all(defined_names_xml.as_bytes())?;
+                names_written = true;
+                skipping = true;
+            }
+            Event::Empty(_) if name == b"definedNames" => {
+                writer.get_mut().write_all(defined_names_xml.as_bytes())?;
+                names_written = true;
+            }
+            Event::End(_) if name == b"definedNames" => skipping = false,
+            _ if skipping => {}
+            event => {
+                // 名前の定義はcalcPrより前に置く
+                let before_names = matches!(&event, Event::Start(_) | Event::Empty(_))
+                    && name == b"calcPr"
+                    || matches!(&event, Event::End(_)) && name == b"workbook";
+                if before_names && !names_written {
+                    writer.get_mut().write_all(defined_names_xml.as_bytes())?;
+                    names_written = true;
+                }
+                writer.write_event(event)?;
+            }
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+/// 前回のシートの並びに、新しく増えたシートを新しいレポートでの前のシートの後ろに挿入する
+fn sheet_order(old: &[&str], new: &[&str]) -> Vec<String> {
+    let mut order: Vec<String> = old.iter().map(|name| name.to_string()).collect();
+    for (i, name) in new.iter().enumerate() {
+        if order.iter().any(|sheet| sheet == name) {
+            continue;
+        }
+        let index = match i.checked_sub(1) {
+            Some(previous) => order
+                .iter()
+                .position(|sheet| sheet == new[previous])
+                .map_or(order.len(), |position| position + 1),
+            None => 0,
+        };
+        order.insert(index, name.to_string());
+    }
+    order
+}
+
+fn workbook_part(package: &Package) -> PackageResult<String> {
+    read_rels(package, "")?
+        .into_iter()
+        .find(|rel| rel.rel_type.ends_with("/officeDocument"))
+        .map(|rel| resolve("", &rel.target))
+        .ok_or_else(|| "the workbook has no main part".into())
+}
+
+fn read_sheets(package: &Package) -> PackageResult<Vec<SheetEntry>> {
+    let workbook = workbook_part(package)?;
+    let rels = read_rels(package, &workbook)?;
+    let mut sheets = vec![];
+    for element in child_elements(package.require(&workbook)?, b"sheets")? {
+        let name = root_attribute(element.as_bytes(), "name")?.unwrap_or_default();
+        let rel_id = relationship_id(element.as_bytes())?.unwrap_or_default();
+        let rel = rels
+            .iter()
+            .find(|rel| rel.id == rel_id)
+            .ok_or_else(|| format!("sheet `{name}` has no relationship"))?;
+        sheets.push(SheetEntry {
+            name,
+            element,
+            rel_type: rel.rel_type.clone(),
+            part: resolve(&workbook, &rel.target),
+        });
+    }
+    Ok(sheets)
+}
+
+fn read_rels(package: &Package, part: &str) -> PackageResult<Vec<Relationship>> {
+    let Some(xml) = package.get(&rels_path(part)) else {
+        return Ok(vec![]);
+    };
+    let mut rels = vec![];
+    for element in child_elements(xml, b"Relationships")? {
+        let element = element.as_bytes();
+        rels.push(Relationship {
+            id: root_attribute(element, "Id")?.unwrap_or_default(),
+            rel_type: root_attribute(element, "Type")?.unwrap_or_default(),
+            target: root_attribute(element, "Target")?.unwrap_or_default(),
+            external: root_attribute(element, "TargetMode")?.as_deref() == Some("External"),
+        });
+    }
+    Ok(rels)
+}
+
+fn write_rels(rels: &[Relationship]) -> PackageResult<Vec<u8>> {
+    let mut writer = Writer::new(vec![]);
+    writer.write_event(Event::Decl(BytesDecl::new(
+        "1.0",
+        Some("UTF-8"),
+        Some("yes"),
+    )))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("Relationships").with_attributes([("xmlns", RELATIONSHIPS_NS)]),
+    ))?;
+    for rel in rels {
+        let mut element = BytesStart::new("Relationship");
+        element.push_attribute(("Id", rel.id.as_str()));
+        element.push_attribute(("Type", rel.rel_type.as_str()));
+        element.push_attribute(("Target", rel.target.as_str()));
+        if rel.external {
+            element.push_attribute(("TargetMode", "External"));
+        }
+        writer.write_event(Event::Empty(element))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("Relationships")))?;
+    Ok(writer.into_inner())
+}
+
+/// `xl/worksheets/sheet1.xml`の関連付けは`xl/worksheets/_rels/sheet1.xml.rels`にある
+fn rels_path(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, file)) => format!("{dir}/_rels/{file}.rels"),
+        None => format!("_rels/{part}.rels"),
+    }
+}
+
+/// 関連付けの参照先を、参照元のファイルからのパッケージ内のパスにする
+fn resolve(source: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut segments: Vec<&str> = match source.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => vec![],
+    };
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                segments.pop();
+            }
+            "." | "" => {}
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// 参照元のファイルから見た参照先の相対パス
+fn relative_target(source: &str, part: &str) -> String {
+    let source_dir: Vec<&str> = match source.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => vec![],
+    };
+    let part_segments: Vec<&str> = part.split('/').collect();
+    let common = source_dir
+        .iter()
+        .zip(&part_segments)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut segments = vec![".."; source_dir.len() - common];
+    segments.extend(&part_segments[common..]);
+    segments.join("/")
+}
+
+/// 同じフォルダで番号を変えた、まだ使われていないファイル名 (`drawing1.xml`→`drawing3.xml`など)
+fn unique_part_name(package: &Package, part: &str) -> String {
+    let (dir, file) = match part.rsplit_once('/') {
+        Some((dir, file)) => (format!("{dir}/"), file),
+        None => (String::new(), part),
+    };
+    let (stem, ext) = match file.rsplit_once('.') {
+        Some((stem, ext)) => (stem, format!(".{ext}")),
+        None => (file, String::new()),
+    };
+    let base = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    (1..)
+        .map(|n| format!("{dir}{base}{n}{ext}"))
+        .find(|name| !package.contains(name))
+        .unwrap()
+}
+
+fn extension(part: &str) -> String {
+    part.rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// `rId3`のような番号付きの名前の次の番号
+fn next_number<'b>(names: impl Iterator<Item = &'b str>, prefix: &str) -> usize {
+    names
+        .filter_map(|name| name.strip_prefix(prefix)?.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+/// 指定した要素の直下の要素を、それぞれXMLの文字列として取り出す
+fn child_elements(xml: &[u8], parent: &[u8]) -> PackageResult<Vec<String>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut children = vec![];
+    let mut in_parent = false;
+    let mut child: Option<(Writer<Vec<u8>>, usize)> = None;
+    loop {
+        let event = reader.read_event()?;
+        if matches!(event, Event::Eof) {
+            break;
+        }
+        if !in_parent {
+            if let Event::Start(e) = &event {
+                in_parent = e.local_name().as_ref() == parent;
+            }
+            continue;
+        }
+        if let Some((writer, depth)) = child.as_mut() {
+            match &event {
+                Event::Start(_) => *depth += 1,
+                Event::End(_) => *depth -= 1,
+                _ => {}
+            }
+            writer.write_event(event)?;
+            if *depth == 0 {
+                let (writer, _) = child.take().unwrap();
+                children.push(String::from_utf8(writer.into_inner())?);
+            }
+            continue;
+        }
+        match event {
+            Event::Start(e) => {
+                let mut writer = Writer::new(vec![]);
+                writer.write_event(Event::Start(e))?;
+                child = Some((writer, 1));
+            }
+            Event::Empty(e) => children.push(to_xml(Event::Empty(e))?),
+            Event::End(_) => break,
+            _ => {}
+        }
+    }
+    Ok(children)
+}
+
+/// 最初の要素(ルート要素)の属性
+fn root_attribute(xml: &[u8], key: &str) -> PackageResult<Option<String>> {
+    let mut reader = Reader::from_reader(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => return attribute(&e, key),
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// 接頭辞付きの`r:id`属性 (関連付けのID)
+fn relationship_id(xml: &[u8]) -> PackageResult<Option<String>> {
+    let mut reader = Reader::from_reader(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => {
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    if attr.key.prefix().is_some() && attr.key.local_name().as_ref() == b"id" {
+                        return Ok(Some(attribute_value(&attr)?));
+                    }
+                }
+                return Ok(None);
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn attribute(e: &BytesStart, key: &str) -> PackageResult<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key.as_ref() == key.as_bytes() {
+            return Ok(Some(attribute_value(&attr)?));
+        }
+    }
+    Ok(None)
+}
+
+fn attribute_value(attr: &Attribute) -> PackageResult<String> {
+    let value = std::str::from_utf8(&attr.value)?;
+    Ok(quick_xml::escape::unescape(value)?.into_owned())
+}
+
+/// 属性を書き換えた要素を作る (Noneを返した属性は取り除く)
+fn map_attributes(
+    e: &BytesStart,
+    mut f: impl FnMut(&str, &str) -> PackageResult<Option<String>>,
+) -> PackageResult<BytesStart<'static>> {
+    let mut element = BytesStart::new(std::str::from_utf8(e.name().as_ref())?.to_string());
+    for attr in e.attributes() {
+        let attr = attr?;
+        let key = std::str::from_utf8(attr.key.as_ref())?;
+        if let Some(value) = f(key, &attribute_value(&attr)?)? {
+            element.push_attribute((key, value.as_str()));
+        }
+    }
+    Ok(element)
+}
+
+/// ルート要素の属性を書き換える (子要素や宣言はそのまま残す)
+fn rewrite_root(
+    xml: &[u8],
+    f: impl FnMut(&str, &str) -> PackageResult<Option<String>>,
+) -> PackageResult<Vec<u8>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(vec![]);
+    let mut f = Some(f);
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(e) if f.is_some() => {
+                let element = map_attributes(&e, f.take().unwrap())?;
+                writer.write_event(Event::Start(element))?;
+            }
+            Event::Empty(e) if f.is_some() => {
+                let element = map_attributes(&e, f.take().unwrap())?;
+                writer.write_event(Event::Empty(element))?;
+            }
+            event => writer.write_event(event)?,
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+fn to_xml(event: Event) -> PackageResult<String> {
+    let mut writer = Writer::new(vec![]);
+    writer.write_event(event)?;
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+/// `<si>...</si>`の中身
+fn inner_xml(element: &str) -> &str {
+    if element.ends_with("/>") {
+        return "";
+    }
+    let start = element.find('>').map_or(0, |i| i + 1);
+    let end = element.rfind("</").unwrap_or(element.len());
+    &element[start..end.max(start)]
+}
+
+/// `x:c`のような要素名の接頭辞 (`x:`、無ければ空)
+fn name_prefix(name: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    match name.split_once(':') {
+        Some((prefix, _)) => format!("{prefix}:"),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{open_workbook, Data, Reader as _, Xlsx};
+    use chrono::NaiveDate;
+    use rust_xlsxwriter::{ExcelDateTime, Format, Note, Table, TableColumn, Workbook};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_changed_years() {
+        let date = |year, day| NaiveDate::from_ymd_opt(year, 1, day).unwrap();
+        let previous_df = df!(
+            "日付" => [date(2024, 1), date(2025, 1), date(2025, 2)],
+            "体調" => [3i32, 4, 2],
+            "コメント" => [None::<&str>, None, None]
+        )
+        .unwrap();
+        let unchanged_df = previous_df.clone();
+        assert!(changed_years(&previous_df, &unchanged_df)
+            .unwrap()
+            .is_empty());
+
+        let merged_df = df!(
+            "日付" => [date(2024, 1), date(2025, 1), date(2025, 2), date(2026, 1)],
+            "体調" => [3i32, 4, 2, 5],
+            "コメント" => [Some("通院"), None, None, None]
+        )
+        .unwrap();
+        assert_eq!(
+            changed_years(&previous_df, &merged_df).unwrap(),
+            [2024, 2026]
+        );
+    }
+
+    #[test]
+    fn test_carry_over_sheets() {
+        let temp_dir = tempdir().unwrap();
+        let previous = temp_dir.path().join("previous.xlsx");
+        let mut workbook = Workbook::new();
+        workbook
+            .add_worksheet()
+            .set_name("data")
+            .unwrap()
+            .write_string(0, 0, "古いデータ")
+            .unwrap();
+        let memo = workbook.add_worksheet().set_name("メモ").unwrap();
+        let bold = Format::new().set_bold().set_num_format("0.000");
+        memo.write_string(0, 0, "通院の記録").unwrap();
+        memo.write_with_format(1, 0, 1.5, &bold).unwrap();
+        memo.insert_note(0, 0, &Note::new("手で追加したメモ"))
+            .unwrap();
+        workbook.save(&previous).unwrap();
+
+        let generated = temp_dir.path().join("generated.xlsx");
+        let mut workbook = Workbook::new();
+        let data = workbook.add_worksheet().set_name("data").unwrap();
+        data.write_string(0, 0, "新しいデータ").unwrap();
+        data.write_with_format(1, 0, 2.0, &Format::new().set_italic())
+            .unwrap();
+        workbook.add_worksheet().set_name("2025").unwrap();
+        workbook.save(&generated).unwrap();
+
+        let output = temp_dir.path().join("output.xlsx");
+        let carried = carry_over_sheets(&previous, &generated, &output, |_| true).unwrap();
+        assert_eq!(carried, ["メモ"]);
+
+        let mut excel: Xlsx<_> = open_workbook(&output).unwrap();
+        assert_eq!(excel.sheet_names(), ["data", "2025", "メモ"]);
+        let memo = excel.worksheet_range("メモ").unwrap();
+        assert_eq!(
+            memo.get_value((0, 0)),
+            Some(&Data::String("通院の記録".into()))
+        );
+        assert_eq!(memo.get_value((1, 0)), Some(&Data::Float(1.5)));
+        let data = excel.worksheet_range("data").unwrap();
+        assert_eq!(
+            data.get_value((0, 0)),
+            Some(&Data::String("新しいデータ".into()))
+        );
+
+        // メモと書式も移っている
+        let package = Package::open(&output).unwrap();
+        let sheets = read_sheets(&package).unwrap();
+        let memo_part = &sheets[2].part;
+        let memo_xml = String::from_utf8(package.get(memo_part).unwrap().to_vec()).unwrap();
+        assert!(memo_xml.contains(r#"t="inlineStr""#));
+        assert!(memo_xml.contains("<legacyDrawing"));
+        let rels = read_rels(&package, memo_part).unwrap();
+        assert!(rels.iter().any(|rel| rel.rel_type.ends_with("/comments")
+            && package.contains(&resolve(memo_part, &rel.target))));
+        let styles = String::from_utf8(package.get("xl/styles.xml").unwrap().to_vec()).unwrap();
+        assert!(styles.contains(r#"formatCode="0.000""#));
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_carry_over_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let columns = || {
+            [
+                TableColumn::new().set_header("日付"),
+                TableColumn::new().set_header("体調"),
+            ]
+        };
+
+        // 利用者が書式・表・名前の定義を加えた前回のレポート
+        let previous = temp_dir.path().join("previous.xlsx");
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("data").unwrap();
+        workbook.add_worksheet().set_name("2024").unwrap();
+        let memo = workbook.add_worksheet().set_name("メモ").unwrap();
+        let day = Format::new()
+            .set_num_format("yyyy\"年\"m\"月\"d\"日\"")
+            .set_background_color("#FFE699");
+        let decimal = Format::new().set_bold().set_num_format("0.000");
+        memo.write_datetime_with_format(1, 0, ExcelDateTime::from_ymd(2025, 1, 6).unwrap(), &day)
+            .unwrap();
+        memo.write_with_format(1, 1, 1.5, &decimal).unwrap();
+        memo.add_table(
+            0,
+            0,
+            1,
+            1,
+            &Table::new().set_name("通院").set_columns(&columns()),
+        )
+        .unwrap();
+        workbook.define_name("対象", "=メモ!$A$2").unwrap();
+        workbook.define_name("期間", "=メモ!$A$1:$B$2").unwrap();
+        workbook.define_name("メモ!範囲", "=メモ!$B$2").unwrap();
+        workbook.define_name("'2024'!範囲", "='2024'!$A$1").unwrap();
+        workbook.save(&previous).unwrap();
+
+        // 同じ名前の表や名前の定義、別の表示形式を持つ新しいレポート
+        let generated = temp_dir.path().join("generated.xlsx");
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("data").unwrap();
+        let sheet = workbook.add_worksheet().set_name("2025").unwrap();
+        sheet
+            .write_with_format(1, 1, 0.25, &Format::new().set_num_format("0.0%"))
+            .unwrap();
+        sheet
+            .add_table(
+                0,
+                0,
+                1,
+                1,
+                &Table::new().set_name("通院").set_columns(&columns()),
+            )
+            .unwrap();
+        workbook.define_name("期間", "='2025'!$A$1:$B$2").unwrap();
+        workbook.save(&generated).unwrap();
+
+        let output = temp_dir.path().join("output.xlsx");
+        let carried =
+            carry_over_sheets(&previous, &generated, &output, |name| name != "2024").unwrap();
+        assert_eq!(carried, ["メモ"]);
+
+        let mut excel: Xlsx<_> = open_workbook(&output).unwrap();
+        assert_eq!(excel.sheet_names(), ["data", "2025", "メモ"]);
+        // 書式の番号がずれても、日付と数値の表示形式はそれぞれのセルに残る
+        let memo = excel.worksheet_range("メモ").unwrap();
+        assert!(matches!(memo.get_value((1, 0)), Some(Data::DateTime(_))));
+        assert_eq!(memo.get_value((1, 1)), Some(&Data::Float(1.5)));
+        let sheet = excel.worksheet_range("2025").unwrap();
+        assert_eq!(sheet.get_value((1, 1)), Some(&Data::Float(0.25)));
+
+        // 重なった名前の定義は新しいレポートのものを使い、移さなかったシートの定義は除く
+        let mut names: Vec<(String, String)> = excel.defined_names().to_vec();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                ("対象".to_string(), "メモ!$A$2".to_string()),
+                ("期間".to_string(), "'2025'!$A$1:$B$2".to_string()),
+                ("範囲".to_string(), "メモ!$B$2".to_string()),
+            ]
+        );
+
+        // 同じ名前の表は名前を変えて移す
+        excel.load_tables().unwrap();
+        let mut tables: Vec<String> = excel.table_names().into_iter().cloned().collect();
+        tables.sort();
+        assert_eq!(tables, ["Table2", "通院"]);
+        assert_eq!(excel.table_names_in_sheet("メモ"), ["Table2"]);
+        let table = excel.table_by_name("Table2").unwrap();
+        assert_eq!(table.columns(), ["日付", "体調"]);
+        assert_eq!(table.data().get((0, 1)), Some(&Data::Float(1.5)));
+        let styles = String::from_utf8(
+            Package::open(&output)
+                .unwrap()
+                .get("xl/styles.xml")
+                .unwrap()
+                .to_vec(),
+        )
+        .unwrap();
+        assert!(styles.contains("FFFFE699"));
+        temp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_sheet_order() {
+        assert_eq!(
+            sheet_order(
+                &["data", "年間体調比較", "2024", "2025", "メモ"],
+                &["data", "年間体調比較", "2025", "2026", "曜日別分析"]
+            ),
+            [
+                "data",
+                "年間体調比較",
+                "2024",
+                "2025",
+                "2026",
+                "曜日別分析",
+                "メモ"
+            ]
+        );
+    }
+}
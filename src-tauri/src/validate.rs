@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use polars::prelude::*;
+use serde::Serialize;
+
+use crate::error::{ConditionError, ConditionResult};
+use crate::scale::ScoreScale;
+use crate::schema::{CONDITION_COLUMN, DATE_COLUMN, PERSON_COLUMN};
+
+/// 読み込んだファイルでの行番号を検証まで保持する列 (結合前に取り除く)
+pub(crate) const ROW_COLUMN: &str = "_row";
+
+/// 検証で見つかった問題の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticKind {
+    /// 体調が設定した範囲(既定は0〜5)の範囲外
+    OutOfRange,
+    /// 今日より後の日付
+    FutureDate,
+    /// 同じファイルに同じ日付(複数人のデータでは同じ日付と人)の行がある
+    DuplicateDate,
+    /// 日付や体調として読み取れないセル
+    Unparseable,
+}
+
+impl DiagnosticKind {
+    /// 検証結果シートに表示する名前
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OutOfRange => "範囲外の値",
+            Self::FutureDate => "未来の日付",
+            Self::DuplicateDate => "重複した日付",
+            Self::Unparseable => "読み取れない値",
+        }
+    }
+}
+
+/// 1行分の検証結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub path: PathBuf,
+    /// ファイルでの行番号 (1から)
+    pub row: Option<usize>,
+    pub date: Option<NaiveDate>,
+    pub column: String,
+    pub value: Option<String>,
+}
+
+/// 読み込んだデータの範囲外の値・未来の日付・重複した日付を探す
+///
+/// 行番号の列があればその行番号を、無ければ見出しを1行目とした行番号を報告する。
+pub fn validate_condition_frame(
+    df: &DataFrame,
+    path: &Path,
+    today: NaiveDate,
+    scale: &ScoreScale,
+) -> PolarsResult<Vec<Diagnostic>> {
+    let dates = df.column(DATE_COLUMN)?.date()?;
+    let conditions = df.column(CONDITION_COLUMN)?.cast(&DataType::Int32)?;
+    let conditions = conditions.i32()?;
+    let persons = match df.column(PERSON_COLUMN) {
+        Ok(persons) => Some(persons.str()?),
+        Err(_) => None,
+    };
+    let rows = match df.column(ROW_COLUMN) {
+        Ok(rows) => Some(rows.u32()?),
+        Err(_) => None,
+    };
+
+    let mut diagnostics = vec![];
+    let mut seen: HashSet<(NaiveDate, Option<&str>)> = HashSet::new();
+    for (i, date) in dates.as_date_iter().enumerate() {
+        let diagnostic = |kind, column: &str, value: Option<String>| Diagnostic {
+            kind,
+            path: path.into(),
+            row: match rows {
+                Some(rows) => rows.get(i).map(|row| row as usize),
+                None => Some(i + 2),
+            },
+            date,
+            column: column.into(),
+            value,
+        };
+        if let Some(condition) = conditions
+            .get(i)
+            .filter(|&condition| !scale.contains(condition))
+        {
+            diagnostics.push(diagnostic(
+                DiagnosticKind::OutOfRange,
+                CONDITION_COLUMN,
+                Some(condition.to_string()),
+            ));
+        }
+        let Some(date) = date else {
+            continue;
+        };
+        if date > today {
+            diagnostics.push(diagnostic(
+                DiagnosticKind::FutureDate,
+                DATE_COLUMN,
+                Some(date.to_string()),
+            ));
+        }
+        let person = persons.and_then(|p| p.get(i));
+        if !seen.insert((date, person)) {
+            diagnostics.push(diagnostic(
+                DiagnosticKind::DuplicateDate,
+                DATE_COLUMN,
+                Some(date.to_string()),
+            ));
+        }
+    }
+    diagnostics.sort_by_key(|d| d.row);
+    Ok(diagnostics)
+}
+
+/// 手で入力した記録を検証する (読み込んだファイルと違い、範囲外の値や未来の日付は保存しない)
+pub(crate) fn validate_entry(
+    date: NaiveDate,
+    condition: Option<i32>,
+    today: NaiveDate,
+    scale: &ScoreScale,
+) -> ConditionResult<()> {
+    if let Some(condition) = condition.filter(|&condition| !scale.contains(condition)) {
+        return Err(ConditionError::InvalidEntry {
+            date,
+            message: format!(
+                "condition {condition} is outside the score scale {}-{}",
+                scale.min, scale.max
+            ),
+        });
+    }
+    if date > today {
+        return Err(ConditionError::InvalidEntry {
+            date,
+            message: "the date is in the future".into(),
+        });
+    }
+    Ok(())
+}
+
+/// 検証してから行番号の列を取り除く
+pub(crate) fn take_validated(
+    df: DataFrame,
+    path: &Path,
+    today: NaiveDate,
+    scale: &ScoreScale,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> PolarsResult<DataFrame> {
+    diagnostics.extend(validate_condition_frame(&df, path, today, scale)?);
+    match df.get_column_index(ROW_COLUMN) {
+        Some(_) => df.drop(ROW_COLUMN),
+        None => Ok(df),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    #[test]
+    fn test_validate_condition_frame() {
+        let df = df!(
+            "日付" => [date(27), date(28), date(27), date(31)],
+            "体調" => [Some(3i32), Some(7), None, Some(-1)],
+            "コメント" => [None::<&str>, None, None, None],
+            ROW_COLUMN => [3u32, 4, 5, 6]
+        )
+        .unwrap();
+        let diagnostics =
+            validate_condition_frame(&df, Path::new("test.csv"), date(30), &ScoreScale::default())
+                .unwrap();
+        let found: Vec<(DiagnosticKind, Option<usize>, Option<&str>)> = diagnostics
+            .iter()
+            .map(|d| (d.kind, d.row, d.value.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticKind::OutOfRange, Some(4), Some("7")),
+                (DiagnosticKind::DuplicateDate, Some(5), Some("2025-01-27")),
+                (DiagnosticKind::OutOfRange, Some(6), Some("-1")),
+                (DiagnosticKind::FutureDate, Some(6), Some("2025-01-31")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_date_for_different_persons_is_not_duplicate() {
+        let df = df!(
+            "日付" => [date(27), date(27)],
+            "人" => ["愛さん", "太郎さん"],
+            "体調" => [3i32, 4]
+        )
+        .unwrap();
+        let diagnostics =
+            validate_condition_frame(&df, Path::new("test.csv"), date(30), &ScoreScale::default())
+                .unwrap();
+        assert!(diagnostics.is_empty());
+    }
+}
@@ -1,12 +1,353 @@
 import { invoke } from "@tauri-apps/api/core";
-import { open, save } from "@tauri-apps/plugin-dialog";
-import { useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { ask, open, save } from "@tauri-apps/plugin-dialog";
+import { useEffect, useState } from "react";
 import "./App.css";
 
+type ConditionError = {
+	kind:
+		| "noInput"
+		| "fileNotFound"
+		| "csvParse"
+		| "csvFormat"
+		| "excelOpen"
+		| "sheetNotFound"
+		| "columnNotFound"
+		| "data"
+		| "report"
+		| "save"
+		| "cancelled"
+		| "internal"
+		| "config"
+		| "store"
+		| "invalidEntry"
+		| "entryExists"
+		| "entryNotFound"
+		| "personRequired";
+	message: string;
+	path: string | null;
+	column: string | null;
+	found: string[];
+};
+
+type ReportConfig = {
+	sheetNames: {
+		data: string;
+		comparison: string;
+		weekdayAnalysis: string;
+		missingDays: string;
+		personComparison: string;
+		conflicts: string;
+		diagnostics: string;
+	};
+	chartLayout: {
+		columns: number;
+		startRow: number;
+		rowOffset: number;
+		columnOffset: number;
+		width: number;
+		height: number;
+		summaryWidth: number;
+		summaryHeight: number;
+	};
+	colors: {
+		holidayFill: string;
+		trendLine: string;
+		rollingMeanLine: string;
+		annualBar: string;
+		monthlyBar: string;
+		missingFill: string;
+	};
+	sections: {
+		monthlyCharts: boolean;
+		yearComparisonCharts: boolean;
+		weekdayAnalysis: boolean;
+		missingDays: boolean;
+		personComparison: boolean;
+		highlightMissingDays: boolean;
+	};
+	scale: {
+		min: number;
+		max: number;
+		lowThreshold: number;
+		axisMin?: number | null;
+		levels: { value: number; label: string; color?: string | null }[];
+	};
+	locale: "ja" | "en";
+};
+
+const sheetNameLabels: [keyof ReportConfig["sheetNames"], string][] = [
+	["data", "生データ"],
+	["comparison", "年間体調比較"],
+	["weekdayAnalysis", "曜日別分析"],
+	["missingDays", "未記録日"],
+	["personComparison", "人別比較"],
+	["conflicts", "値が異なっていた項目"],
+	["diagnostics", "検証結果"],
+];
+
+const chartLayoutLabels: [keyof ReportConfig["chartLayout"], string][] = [
+	["columns", "横に並べるグラフの数"],
+	["startRow", "最初のグラフの行"],
+	["rowOffset", "グラフの間隔 (行)"],
+	["columnOffset", "グラフの間隔 (列)"],
+	["width", "グラフの幅 (px)"],
+	["height", "グラフの高さ (px)"],
+	["summaryWidth", "集計表のグラフの幅 (px)"],
+	["summaryHeight", "集計表のグラフの高さ (px)"],
+];
+
+const colorLabels: [keyof ReportConfig["colors"], string][] = [
+	["holidayFill", "土日祝の背景"],
+	["trendLine", "体調の折れ線"],
+	["rollingMeanLine", "7日平均の折れ線"],
+	["annualBar", "年間の日数のデータバー"],
+	["monthlyBar", "月毎の日数のデータバー"],
+	["missingFill", "未記録の日の行"],
+];
+
+const sectionLabels: [keyof ReportConfig["sections"], string][] = [
+	["monthlyCharts", "年毎のシートに月毎のグラフを入れる"],
+	["yearComparisonCharts", "年間体調比較シートに年毎の比較グラフを入れる"],
+	["weekdayAnalysis", "曜日別分析シートを作成する"],
+	["missingDays", "未記録日シートを作成する"],
+	["personComparison", "人別比較シートを作成する (複数人のデータ)"],
+	["highlightMissingDays", "年毎のシートで未記録の日に色を付ける"],
+];
+
+type ReportProgress = { jobId: string } & (
+	| { stage: "parsing"; path: string; index: number; total: number }
+	| { stage: "merging"; index: number; total: number }
+	| {
+			stage: "sheet";
+			year: number;
+			person: string | null;
+			index: number;
+			total: number;
+	  }
+	| { stage: "saving" }
+);
+
+function progressMessage(progress: ReportProgress): string {
+	switch (progress.stage) {
+		case "parsing":
+			return `読み込み中 (${progress.index}/${progress.total}): ${progress.path.split("/").pop()}`;
+		case "merging":
+			return `結合中 (${progress.index}/${progress.total})`;
+		case "sheet":
+			return `${progress.year}年${progress.person ? ` ${progress.person}` : ""}のシートを作成中 (${progress.index}/${progress.total})`;
+		case "saving":
+			return "保存中";
+	}
+}
+
+// 全体を100とした進み具合 (年毎のシートの作成に最も時間がかかる)
+function progressPercent(progress: ReportProgress): number {
+	switch (progress.stage) {
+		case "parsing":
+			return (10 * progress.index) / progress.total;
+		case "merging":
+			return 10 + (10 * progress.index) / progress.total;
+		case "sheet":
+			return 20 + (75 * progress.index) / progress.total;
+		case "saving":
+			return 95;
+	}
+}
+
+type MergeStrategy =
+	| "preferExcel"
+	| "preferCsv"
+	| "preferNonNull"
+	| "concatComments";
+
+type MergeConflict = {
+	date: string;
+	person: string | null;
+	column: string;
+	excel: string | null;
+	csv: string | null;
+	resolved: string | null;
+};
+
+function conflictMessage(conflicts: MergeConflict[]): string {
+	const value = (v: string | null) => v ?? "(空欄)";
+	return conflicts
+		.map(
+			(c) =>
+				`${c.date}${c.person ? ` ${c.person}` : ""} ${c.column}: ${value(c.excel)} → ${value(c.csv)} (採用: ${value(c.resolved)})`,
+		)
+		.join("\n");
+}
+
+type Diagnostic = {
+	kind: "outOfRange" | "futureDate" | "duplicateDate" | "unparseable";
+	path: string;
+	row: number | null;
+	date: string | null;
+	column: string;
+	value: string | null;
+};
+
+type ImportBatch = {
+	id: number;
+	importedAt: string;
+	kind: "files" | "manual";
+	sources: { path: string; sha256: string }[];
+	added: number;
+	changed: number;
+	rolledBackAt: string | null;
+};
+
+type RollbackSummary = {
+	id: number;
+	restored: number;
+	skipped: ConditionEntry[];
+};
+
+type ReportSummary = {
+	conflicts: MergeConflict[];
+	diagnostics: Diagnostic[];
+	update: { years: number[]; carriedSheets: string[] } | null;
+	import: ImportBatch | null;
+};
+
+type ConditionEntry = {
+	date: string;
+	person: string | null;
+	condition: number | null;
+	comment: string | null;
+	extra: Record<string, string | number | boolean>;
+};
+
+type DataPreview = {
+	rows: {
+		date: string | null;
+		person: string | null;
+		condition: number | null;
+		comment: string | null;
+	}[];
+	yearly: {
+		year: number;
+		person: string | null;
+		counts: {
+			trend: string;
+			condition: number;
+			annual: number;
+			monthly: number[];
+		}[];
+	}[];
+	summary: {
+		rowCount: number;
+		recordedDays: number;
+		firstDate: string | null;
+		lastDate: string | null;
+		meanCondition: number | null;
+		persons: string[];
+	};
+	conflicts: MergeConflict[];
+	diagnostics: Diagnostic[];
+};
+
+function diagnosticMessage(diagnostics: Diagnostic[]): string {
+	const labels = {
+		outOfRange: "範囲外の値",
+		futureDate: "未来の日付",
+		duplicateDate: "重複した日付",
+		unparseable: "読み取れない値",
+	};
+	return diagnostics
+		.map(
+			(d) =>
+				`${d.path.split("/").pop()} ${d.row !== null ? `${d.row}行目` : ""} ${d.column}: ${labels[d.kind]} (${d.value ?? ""})`,
+		)
+		.join("\n");
+}
+
+function errorMessage(error: ConditionError): string {
+	const file = error.path ? error.path.split("/").pop() : "";
+	switch (error.kind) {
+		case "noInput":
+			return "csvファイルかエクセルファイルを選択してください";
+		case "fileNotFound":
+			return `ファイルが見つかりません: ${file}`;
+		case "csvParse":
+			return `CSVファイルを読み込めません: ${file}\n${error.message}`;
+		case "csvFormat":
+			return `Rhythm CareのCSVの形式を判定できません: ${file}\n${error.message}\n見つかった内容:\n${error.found.join("\n")}`;
+		case "excelOpen":
+			return `エクセルファイルを開けません: ${file}`;
+		case "sheetNotFound":
+			return `エクセルファイルに data シートがありません: ${file}`;
+		case "columnNotFound":
+			return `エクセルファイルの data シートに「${error.column}」列がありません: ${file}`;
+		case "save":
+			return `ファイルを保存できません。他のアプリで開いていないか確認してください: ${file}`;
+		case "cancelled":
+			return "レポートの作成を中止しました";
+		case "config":
+			return `レポートの設定が正しくありません: ${file}\n${error.message}`;
+		case "store":
+			return `保存されているデータを読み書きできません: ${file}\n${error.message}`;
+		case "invalidEntry":
+			return `入力した記録を保存できません (範囲外の体調や未来の日付):\n${error.message}`;
+		case "entryExists":
+			return "この日付の記録は既にあります。修正する場合はUpdateを押してください";
+		case "entryNotFound":
+			return "この日付の記録はありません";
+		case "personRequired":
+			return `複数人のデータに結合するため、読み込む人の名前を入力してください (${error.message})`;
+		default:
+			return `エラーが発生しました:\n${error.message}`;
+	}
+}
+
 function App() {
-	const [csv_path, setCSVPath] = useState("");
+	const [csv_paths, setCSVPaths] = useState<string[]>([]);
 	const [excel_path, setExcelPath] = useState("");
+	const [update_excel, setUpdateExcel] = useState(false);
+	const [custom_holidays, setCustomHolidays] = useState("");
+	const [merge_strategy, setMergeStrategy] =
+		useState<MergeStrategy>("preferCsv");
+	const [conflict_sheet, setConflictSheet] = useState(false);
+	const [all_persons, setAllPersons] = useState(false);
+	const [csv_person, setCSVPerson] = useState("");
+	const [conflict_message, setConflictMsg] = useState("");
+	const [diagnostics_sheet, setDiagnosticsSheet] = useState(false);
+	const [diagnostic_message, setDiagnosticMsg] = useState("");
+	const [job_id, setJobId] = useState<string | null>(null);
+	const [progress, setProgress] = useState<ReportProgress | null>(null);
+	const [preview, setPreview] = useState<DataPreview | null>(null);
+	const [entries, setEntries] = useState<ConditionEntry[] | null>(null);
+	const [entries_from, setEntriesFrom] = useState("");
+	const [entries_to, setEntriesTo] = useState("");
+	const [entry_form, setEntryForm] = useState({
+		date: "",
+		person: "",
+		condition: "",
+		comment: "",
+	});
+	const [entry_message, setEntryMsg] = useState("");
+	const [imports, setImports] = useState<ImportBatch[] | null>(null);
+	const [import_message, setImportMsg] = useState("");
 	const [result_message, setResultMsg] = useState("");
+	const [config, setConfig] = useState<ReportConfig | null>(null);
+	const [config_message, setConfigMsg] = useState("");
+
+	useEffect(() => {
+		invoke<ReportConfig>("get_report_config")
+			.then(setConfig)
+			.catch((error: ConditionError) => setConfigMsg(errorMessage(error)));
+	}, []);
+
+	function save_config() {
+		if (config === null) {
+			return;
+		}
+		invoke("save_report_config", { config })
+			.then(() => setConfigMsg("レポートの設定を保存しました"))
+			.catch((error: ConditionError) => setConfigMsg(errorMessage(error)));
+	}
 
 	function open_dialog(file_type: string) {
 		let filter: { name: string; extensions: string[] }[];
@@ -27,52 +368,202 @@ function App() {
 		} else {
 			return;
 		}
-		open({ multiple: false, filters: filter, directory: false }).then(
-			(files) => {
-				if (files && files.length > 0) {
-					if (file_type === "csv") {
-						setCSVPath(files);
-					} else if (file_type === "excel") {
-						setExcelPath(files);
-					}
-				} else {
-					if (file_type === "csv") {
-						setCSVPath("No file selected");
-					} else if (file_type === "excel") {
-						setExcelPath("No file selected");
-					}
-				}
-			},
-		);
+		if (file_type === "csv") {
+			open({ multiple: true, filters: filter, directory: false }).then(
+				(files) => {
+					setCSVPaths(files ?? []);
+				},
+			);
+		} else {
+			open({ multiple: false, filters: filter, directory: false }).then(
+				(file) => {
+					setExcelPath(file ?? "");
+				},
+			);
+		}
 	}
 
 	async function save_excel() {
-		const save_path = await save({
-			defaultPath: "体調管理.xlsx",
-			filters: [
-				{
-					name: "Excel file",
-					extensions: ["xlsx"],
-				},
-			],
-		});
+		const update = update_excel && excel_path !== "";
+		const save_path = update
+			? excel_path
+			: await save({
+					defaultPath: "体調管理.xlsx",
+					filters: [
+						{
+							name: "Excel file",
+							extensions: ["xlsx"],
+						},
+					],
+				});
 		if (save_path === null) {
 			setResultMsg("保存先が選択されていません");
 			return;
 		}
-		invoke("write_excel", {
-			csvPath: csv_path,
-			excelPath: excel_path,
+		setConflictMsg("");
+		setDiagnosticMsg("");
+		const id = crypto.randomUUID();
+		setJobId(id);
+		const unlisten = await listen<ReportProgress>(
+			"report-progress",
+			(event) => {
+				if (event.payload.jobId === id) {
+					setProgress(event.payload);
+				}
+			},
+		);
+		invoke<ReportSummary>("write_excel", {
+			jobId: id,
+			csvPaths: csv_paths,
+			excelPath: excel_path === "" ? null : excel_path,
 			savePath: save_path,
+			customHolidays: custom_holidays
+				.split(/\s+/)
+				.filter((date) => date !== ""),
+			options: load_options(),
+			sheets: { conflicts: conflict_sheet, diagnostics: diagnostics_sheet },
+			update,
 		})
-			.then(() => {
-				setResultMsg("更新された体調の管理エクセルを保存しました");
+			.then(({ conflicts, diagnostics, update, import: batch }) => {
+				const imported =
+					batch !== null
+						? ` (追加 ${batch.added}件、変更 ${batch.changed}件)`
+						: "";
+				if (update !== null) {
+					setResultMsg(
+						`前回のエクセルファイルを更新しました${imported} (作り直した年: ${update.years.join(", ") || "なし"}、残したシート: ${update.carriedSheets.join(", ") || "なし"})`,
+					);
+				} else {
+					setResultMsg(`更新された体調の管理エクセルを保存しました${imported}`);
+				}
+				if (diagnostics.length > 0) {
+					setDiagnosticMsg(
+						`データの確認が必要な行 (${diagnostics.length}件):\n${diagnosticMessage(diagnostics)}`,
+					);
+				}
+				if (conflicts.length > 0) {
+					setConflictMsg(
+						`値が異なっていた項目 (${conflicts.length}件):\n${conflictMessage(conflicts)}`,
+					);
+				}
 			})
-			.catch((error) => {
-				setResultMsg(`エラーが発生しました:\n${error}`);
+			.catch((error: ConditionError) => {
+				setResultMsg(errorMessage(error));
+			})
+			.finally(() => {
+				unlisten();
+				setJobId(null);
+				setProgress(null);
+			});
+	}
+
+	function load_options() {
+		return {
+			csv: {
+				allPersons: all_persons,
+				person: csv_person === "" ? null : csv_person,
+			},
+			mergeStrategy: merge_strategy,
+		};
+	}
+
+	function preview_data() {
+		setPreview(null);
+		invoke<DataPreview>("preview_data", {
+			csvPaths: csv_paths,
+			excelPath: excel_path === "" ? null : excel_path,
+			options: load_options(),
+		})
+			.then((data) => {
+				setPreview(data);
+				setResultMsg("");
+			})
+			.catch((error: ConditionError) => {
+				setResultMsg(errorMessage(error));
+			});
+	}
+
+	function list_entries() {
+		invoke<ConditionEntry[]>("list_entries", {
+			from: entries_from === "" ? null : entries_from,
+			to: entries_to === "" ? null : entries_to,
+		})
+			.then((data) => {
+				setEntries(data);
+				setResultMsg("");
+			})
+			.catch((error: ConditionError) => {
+				setResultMsg(errorMessage(error));
 			});
 	}
 
+	function edit_entry(command: "add_entry" | "update_entry" | "delete_entry") {
+		if (entry_form.date === "") {
+			setEntryMsg("日付を入力してください");
+			return;
+		}
+		const person = entry_form.person === "" ? null : entry_form.person;
+		const args =
+			command === "delete_entry"
+				? { date: entry_form.date, person }
+				: {
+						entry: {
+							date: entry_form.date,
+							person,
+							condition:
+								entry_form.condition === "" ? null : Number(entry_form.condition),
+							comment: entry_form.comment === "" ? null : entry_form.comment,
+						},
+					};
+		invoke(command, args)
+			.then(() => {
+				setEntryMsg(
+					{
+						add_entry: "記録を追加しました",
+						update_entry: "記録を修正しました",
+						delete_entry: "記録を削除しました",
+					}[command],
+				);
+				list_entries();
+			})
+			.catch((error: ConditionError) => setEntryMsg(errorMessage(error)));
+	}
+
+	function list_imports() {
+		invoke<ImportBatch[]>("list_imports")
+			.then(setImports)
+			.catch((error: ConditionError) => setImportMsg(errorMessage(error)));
+	}
+
+	async function rollback_import(batch: ImportBatch) {
+		const confirmed = await ask(
+			`${batch.importedAt.slice(0, 16).replace("T", " ")} の取り込み (追加 ${batch.added}件、変更 ${batch.changed}件) を取り消しますか?`,
+		);
+		if (!confirmed) {
+			return;
+		}
+		invoke<RollbackSummary>("rollback_import", { id: batch.id })
+			.then(({ restored, skipped }) => {
+				setImportMsg(
+					[
+						`${restored}件を取り込む前の値に戻しました`,
+						...skipped.map(
+							(entry) =>
+								`${entry.date}${entry.person ? ` ${entry.person}` : ""}: 後の取り込みで変更されているため戻していません`,
+						),
+					].join("\n"),
+				);
+				list_imports();
+			})
+			.catch((error: ConditionError) => setImportMsg(errorMessage(error)));
+	}
+
+	function cancel_report() {
+		if (job_id !== null) {
+			invoke("cancel_report", { jobId: job_id });
+		}
+	}
+
 	return (
 		<main className="container">
 			<h1>体調データ解析アプリ</h1>
@@ -81,11 +572,33 @@ function App() {
 				<p>1. Rhythm Careからデータをcsvで保存する(エクスポート)</p>
 
 				<div>
-					<p>2. csvファイルをアップロードする</p>
+					<p>2. csvファイルをアップロードする(複数選択可)</p>
 					<button type="button" onClick={() => open_dialog("csv")}>
 						Upload CSV
 					</button>
-					<p>Selected CSV file: {csv_path.split("/").pop()}</p>
+					<p>
+						Selected CSV file:{" "}
+						{csv_paths.length > 0
+							? csv_paths.map((path) => path.split("/").pop()).join(", ")
+							: "No file selected"}
+					</p>
+					<label>
+						<input
+							type="checkbox"
+							checked={all_persons}
+							onChange={(e) => setAllPersons(e.target.checked)}
+						/>
+						csvにある全員の体調を読み込み、人毎のシートを作成する
+					</label>
+					<p>
+						(任意) 読み込む人の名前
+						(人の列が無いcsvを複数人のデータに結合する場合は必須)
+					</p>
+					<input
+						value={csv_person}
+						placeholder="愛さん"
+						onChange={(e) => setCSVPerson(e.target.value)}
+					/>
 				</div>
 
 				<div>
@@ -93,15 +606,390 @@ function App() {
 					<button type="button" onClick={() => open_dialog("excel")}>
 						Upload Excel
 					</button>
-					<p>Selected Excel file: {excel_path.split("/").pop()}</p>
+					<p>
+						Selected Excel file:{" "}
+						{excel_path !== "" ? excel_path.split("/").pop() : "No file selected"}
+					</p>
+					{excel_path !== "" && (
+						<>
+							<button type="button" onClick={() => setExcelPath("")}>
+								Clear Excel
+							</button>
+							<label>
+								<input
+									type="checkbox"
+									checked={update_excel}
+									onChange={(e) => setUpdateExcel(e.target.checked)}
+								/>
+								前回のエクセルファイルを上書きし、データが変わった年のシートだけを作り直す
+								(追加したシートや書式は残し、既存の日付はエクセルの値を優先する)
+							</label>
+						</>
+					)}
+				</div>
+
+				<div>
+					<p>
+						(任意) 祝日以外に休日として扱う日付を入力する
+						(YYYY-MM-DD、改行区切り)
+					</p>
+					<textarea
+						rows={3}
+						value={custom_holidays}
+						placeholder="2025-08-13"
+						onChange={(e) => setCustomHolidays(e.target.value)}
+					/>
+				</div>
+
+				<div>
+					<p>(任意) 同じ日付のデータがある場合の扱い</p>
+					<select
+						value={merge_strategy}
+						onChange={(e) => setMergeStrategy(e.target.value as MergeStrategy)}
+					>
+						<option value="preferCsv">CSVを優先</option>
+						<option value="preferExcel">エクセルを優先</option>
+						<option value="preferNonNull">CSVを優先 (空欄はエクセルの値を残す)</option>
+						<option value="concatComments">コメントを連結</option>
+					</select>
+					<label>
+						<input
+							type="checkbox"
+							checked={conflict_sheet}
+							onChange={(e) => setConflictSheet(e.target.checked)}
+						/>
+						値が異なっていた項目をconflictsシートに書き出す
+					</label>
+					<label>
+						<input
+							type="checkbox"
+							checked={diagnostics_sheet}
+							onChange={(e) => setDiagnosticsSheet(e.target.checked)}
+						/>
+						範囲外の値や重複した日付などを検証結果シートに書き出す
+					</label>
+				</div>
+
+				<div>
+					<p>(任意) 保存する前に結合結果を確認する</p>
+					<button type="button" onClick={() => preview_data()}>
+						Preview
+					</button>
+					{preview !== null && (
+						<div>
+							<p>
+								{preview.summary.firstDate ?? "-"} 〜{" "}
+								{preview.summary.lastDate ?? "-"}: {preview.summary.rowCount}
+								行 (体調の記録 {preview.summary.recordedDays}日、平均{" "}
+								{preview.summary.meanCondition?.toFixed(2) ?? "-"})
+								{preview.summary.persons.length > 0 &&
+									` / ${preview.summary.persons.join(", ")}`}
+							</p>
+							{preview.diagnostics.length > 0 && (
+								<p style={{ whiteSpace: "pre-wrap" }}>
+									{diagnosticMessage(preview.diagnostics)}
+								</p>
+							)}
+							{preview.yearly.map((yearly) => (
+								<table key={`${yearly.year}-${yearly.person ?? ""}`}>
+									<caption>
+										{yearly.year}年{yearly.person && ` ${yearly.person}`}
+									</caption>
+									<thead>
+										<tr>
+											<th>調子</th>
+											<th>年間</th>
+											{yearly.counts[0]?.monthly.map((_, i) => (
+												<th key={`${i + 1}月`}>{i + 1}月</th>
+											))}
+										</tr>
+									</thead>
+									<tbody>
+										{yearly.counts.map((count) => (
+											<tr key={count.condition}>
+												<td>{count.trend}</td>
+												<td>
+													<div
+														style={{
+															background: "orange",
+															width: `${count.annual / 3.65}%`,
+															minWidth: count.annual > 0 ? "2px" : 0,
+														}}
+													>
+														{count.annual}
+													</div>
+												</td>
+												{count.monthly.map((days, i) => (
+													<td key={`${i + 1}月`}>{days}</td>
+												))}
+											</tr>
+										))}
+									</tbody>
+								</table>
+							))}
+						</div>
+					)}
 				</div>
 
+				<div>
+					<p>(任意) これまでに取り込んだデータを確認する</p>
+					<input
+						type="date"
+						value={entries_from}
+						onChange={(e) => setEntriesFrom(e.target.value)}
+					/>
+					〜
+					<input
+						type="date"
+						value={entries_to}
+						onChange={(e) => setEntriesTo(e.target.value)}
+					/>
+					<button type="button" onClick={() => list_entries()}>
+						Show Data
+					</button>
+					{entries !== null && (
+						<table>
+							<caption>{entries.length}件</caption>
+							<thead>
+								<tr>
+									<th>日付</th>
+									<th>人</th>
+									<th>体調</th>
+									<th>コメント</th>
+								</tr>
+							</thead>
+							<tbody>
+								{entries.map((entry) => (
+									<tr
+										key={`${entry.date}-${entry.person ?? ""}`}
+										onClick={() =>
+											setEntryForm({
+												date: entry.date,
+												person: entry.person ?? "",
+												condition: entry.condition?.toString() ?? "",
+												comment: entry.comment ?? "",
+											})
+										}
+									>
+										<td>{entry.date}</td>
+										<td>{entry.person ?? ""}</td>
+										<td>{entry.condition ?? ""}</td>
+										<td>{entry.comment ?? ""}</td>
+									</tr>
+								))}
+							</tbody>
+						</table>
+					)}
+					<p>記録を追加・修正する (一覧の行を押すと入力欄に読み込む)</p>
+					<input
+						type="date"
+						value={entry_form.date}
+						onChange={(e) => setEntryForm({ ...entry_form, date: e.target.value })}
+					/>
+					<input
+						value={entry_form.person}
+						placeholder="人 (複数人のデータのみ)"
+						onChange={(e) =>
+							setEntryForm({ ...entry_form, person: e.target.value })
+						}
+					/>
+					<select
+						value={entry_form.condition}
+						onChange={(e) =>
+							setEntryForm({ ...entry_form, condition: e.target.value })
+						}
+					>
+						<option value="">(体調なし)</option>
+						{config?.scale.levels.map((level) => (
+							<option key={level.value} value={level.value}>
+								{level.value} {level.label}
+							</option>
+						))}
+					</select>
+					<input
+						value={entry_form.comment}
+						placeholder="コメント"
+						onChange={(e) =>
+							setEntryForm({ ...entry_form, comment: e.target.value })
+						}
+					/>
+					<button type="button" onClick={() => edit_entry("add_entry")}>
+						Add
+					</button>
+					<button type="button" onClick={() => edit_entry("update_entry")}>
+						Update
+					</button>
+					<button type="button" onClick={() => edit_entry("delete_entry")}>
+						Delete
+					</button>
+					<p style={{ whiteSpace: "pre-wrap" }}>{entry_message}</p>
+				</div>
+
+				<div>
+					<p>(任意) 取り込みの履歴を確認し、間違えて取り込んだものを取り消す</p>
+					<button type="button" onClick={() => list_imports()}>
+						Show Imports
+					</button>
+					{imports !== null && (
+						<table>
+							<thead>
+								<tr>
+									<th>日時</th>
+									<th>ファイル</th>
+									<th>追加</th>
+									<th>変更</th>
+									<th />
+								</tr>
+							</thead>
+							<tbody>
+								{imports.map((batch) => (
+									<tr key={batch.id}>
+										<td>{batch.importedAt.slice(0, 16).replace("T", " ")}</td>
+										<td>
+											{batch.kind === "manual"
+												? "手入力"
+												: batch.sources
+														.map((source) => source.path.split("/").pop())
+														.join(", ")}
+										</td>
+										<td>{batch.added}</td>
+										<td>{batch.changed}</td>
+										<td>
+											{batch.rolledBackAt !== null ? (
+												"取り消し済み"
+											) : (
+												<button
+													type="button"
+													onClick={() => rollback_import(batch)}
+												>
+													Undo
+												</button>
+											)}
+										</td>
+									</tr>
+								))}
+							</tbody>
+						</table>
+					)}
+					<p style={{ whiteSpace: "pre-wrap" }}>{import_message}</p>
+				</div>
+
+				{config !== null && (
+					<details>
+						<summary>(任意) レポートの設定</summary>
+						<p>シート名</p>
+						{sheetNameLabels.map(([key, label]) => (
+							<label key={key}>
+								{label}
+								<input
+									value={config.sheetNames[key]}
+									onChange={(e) =>
+										setConfig({
+											...config,
+											sheetNames: { ...config.sheetNames, [key]: e.target.value },
+										})
+									}
+								/>
+							</label>
+						))}
+						<p>グラフの配置</p>
+						{chartLayoutLabels.map(([key, label]) => (
+							<label key={key}>
+								{label}
+								<input
+									type="number"
+									min={0}
+									value={config.chartLayout[key]}
+									onChange={(e) =>
+										setConfig({
+											...config,
+											chartLayout: {
+												...config.chartLayout,
+												[key]: Number(e.target.value),
+											},
+										})
+									}
+								/>
+							</label>
+						))}
+						<p>色</p>
+						{colorLabels.map(([key, label]) => (
+							<label key={key}>
+								{label}
+								<input
+									type="color"
+									value={config.colors[key]}
+									onChange={(e) =>
+										setConfig({
+											...config,
+											colors: { ...config.colors, [key]: e.target.value.toUpperCase() },
+										})
+									}
+								/>
+							</label>
+						))}
+						<p>出力するシートとグラフ</p>
+						{sectionLabels.map(([key, label]) => (
+							<label key={key}>
+								<input
+									type="checkbox"
+									checked={config.sections[key]}
+									onChange={(e) =>
+										setConfig({
+											...config,
+											sections: { ...config.sections, [key]: e.target.checked },
+										})
+									}
+								/>
+								{label}
+							</label>
+						))}
+						<label>
+							レポートの言語
+							<select
+								value={config.locale}
+								onChange={(e) =>
+									setConfig({
+										...config,
+										locale: e.target.value as ReportConfig["locale"],
+									})
+								}
+							>
+								<option value="ja">日本語</option>
+								<option value="en">English</option>
+							</select>
+						</label>
+						<button type="button" onClick={() => save_config()}>
+							Save Settings
+						</button>
+						<p style={{ whiteSpace: "pre-wrap" }}>{config_message}</p>
+					</details>
+				)}
+
 				<div>
 					<p>4. 解析結果(エクセル)を保存する</p>
-					<button type="button" onClick={() => save_excel()}>
+					<button
+						type="button"
+						disabled={job_id !== null}
+						onClick={() => save_excel()}
+					>
 						Save Excel
 					</button>
+					{job_id !== null && (
+						<div>
+							<progress
+								max={100}
+								value={progress ? progressPercent(progress) : 0}
+							/>
+							<span>{progress ? progressMessage(progress) : "準備中"}</span>
+							<button type="button" onClick={() => cancel_report()}>
+								Cancel
+							</button>
+						</div>
+					)}
 					<p>{result_message}</p>
+					<p style={{ whiteSpace: "pre-wrap" }}>{diagnostic_message}</p>
+					<p style={{ whiteSpace: "pre-wrap" }}>{conflict_message}</p>
 				</div>
 			</div>
 		</main>